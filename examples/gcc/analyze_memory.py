@@ -1,31 +1,28 @@
 #!/usr/bin/env python3
-"""Parse /usr/bin/time -v stderr output into resource metrics.
+"""Report resource metrics from the observation's `rusage` block.
 
-/usr/bin/time -v emits lines like:
-    Maximum resident set size (kbytes): 12345
-    Minor (reclaiming a frame) page faults: 6789
-    Voluntary context switches: 42
+fossil records what wait4(2) returns for every iteration, e.g.:
+    "rusage": {"max_rss_kb": 12345, "minor_faults": 6789, ...}
 
-Emits: wall_time_ms, peak_rss_kb, minor_faults, voluntary_ctx_switches
+Emits: wall_time_ms, cpu_time_ms, peak_rss_kb, minor_faults,
+major_faults, voluntary_ctx_switches, involuntary_ctx_switches
 """
-import json, sys, re
+import json, sys
 
 obs = json.load(sys.stdin)
+ru = obs.get("rusage") or {}
 
 metrics = {"wall_time_ms": obs.get("wall_time_us", 0) / 1000.0}
 
-patterns = {
-    "peak_rss_kb": r"Maximum resident set size \(kbytes\):\s+(\d+)",
-    "minor_faults": r"Minor \(reclaiming a frame\) page faults:\s+(\d+)",
-    "major_faults": r"Major \(requiring I/O\) page faults:\s+(\d+)",
-    "voluntary_ctx_switches": r"Voluntary context switches:\s+(\d+)",
-    "involuntary_ctx_switches": r"Involuntary context switches:\s+(\d+)",
-}
-
-for line in obs.get("stderr", []):
-    for metric_name, pattern in patterns.items():
-        m = re.search(pattern, line)
-        if m:
-            metrics[metric_name] = int(m.group(1))
+if ru:
+    metrics["cpu_time_ms"] = (ru["user_time_us"] + ru["system_time_us"]) / 1000.0
+    metrics["peak_rss_kb"] = ru["max_rss_kb"]
+    for key in (
+        "minor_faults",
+        "major_faults",
+        "voluntary_ctx_switches",
+        "involuntary_ctx_switches",
+    ):
+        metrics[key] = ru[key]
 
 json.dump(metrics, sys.stdout)
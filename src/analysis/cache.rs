@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use crate::error::FossilError;
+
+/// Directory inside a record holding cached script output.
+const CACHE_DIR: &str = ".analysis-cache";
+
+#[derive(Deserialize, Serialize)]
+struct Entry {
+    key: String,
+    outputs: Vec<Value>,
+}
+
+/// [Fossil Doc] `ScriptCache`
+/// -------------------------------------------------------------
+/// Parsed per-observation output of one analysis script over one
+/// record, kept in the record's `.analysis-cache` so analyzing it
+/// again needn't spawn the script per observation. Each script has
+/// one entry, valid while the script, the record's results.json and
+/// whether warmups were included are what they were when it was
+/// written; a mismatch just means running the script again and
+/// overwriting the entry. The directory ignores itself, so caching
+/// never dirties the project repo.
+pub struct ScriptCache {
+    file: PathBuf,
+    key: String,
+}
+
+impl ScriptCache {
+    pub fn new(
+        script: &Path,
+        run_dir: &Path,
+        include_warmup: bool,
+    ) -> Result<Self, FossilError> {
+        let mut hasher = Sha256::new();
+        hasher.update(std::fs::read(script)?);
+        hasher.update(std::fs::read(run_dir.join("results.json"))?);
+        hasher.update([include_warmup as u8]);
+        let key = format!("{:x}", hasher.finalize());
+
+        // Name the entry after the script, with a hash of its full path
+        // so two `perf.py` in different directories don't collide.
+        let path_hash = format!(
+            "{:x}",
+            Sha256::digest(script.as_os_str().as_encoded_bytes())
+        );
+        let stem = script
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let warmup = if include_warmup { "+warmup" } else { "" };
+        let file = run_dir
+            .join(CACHE_DIR)
+            .join(format!("{stem}-{}{warmup}.json", &path_hash[..8]));
+        Ok(Self { file, key })
+    }
+
+    /// The cached outputs, if the entry is current. Unreadable or
+    /// corrupt entries count as missing.
+    pub fn load(&self) -> Option<Vec<Value>> {
+        let raw = std::fs::read(&self.file).ok()?;
+        let entry: Entry = serde_json::from_slice(&raw).ok()?;
+        (entry.key == self.key).then_some(entry.outputs)
+    }
+
+    /// Replace the entry with `outputs`. Written to a temporary file
+    /// first, so a reader never sees half an entry.
+    pub fn store(&self, outputs: &[Value]) -> Result<(), FossilError> {
+        let dir = self
+            .file
+            .parent()
+            .expect("entry is inside the cache dir");
+        std::fs::create_dir_all(dir)?;
+        let ignore = dir.join(".gitignore");
+        if !ignore.exists() {
+            std::fs::write(ignore, "*\n")?;
+        }
+        let entry = Entry {
+            key: self.key.clone(),
+            outputs: outputs.to_vec(),
+        };
+        let tmp = self
+            .file
+            .with_extension(format!("tmp{}", std::process::id()));
+        std::fs::write(
+            &tmp,
+            serde_json::to_vec(&entry).map_err(|e| {
+                FossilError::InvalidConfig(format!("serializing cache: {e}"))
+            })?,
+        )?;
+        std::fs::rename(&tmp, &self.file)?;
+        Ok(())
+    }
+
+    /// Delete a record's cache, returning the bytes freed.
+    pub fn purge(run_dir: &Path) -> Result<u64, FossilError> {
+        let dir = run_dir.join(CACHE_DIR);
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            return Ok(0);
+        };
+        let bytes = entries
+            .filter_map(|e| e.ok()?.metadata().ok())
+            .map(|m| m.len())
+            .sum();
+        std::fs::remove_dir_all(&dir)?;
+        Ok(bytes)
+    }
+}
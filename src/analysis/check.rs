@@ -0,0 +1,314 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::Samples;
+use super::stats::{self, SignificanceTest};
+use super::table::{Align, write_table};
+
+/// [Fossil Doc] `Threshold`
+/// -------------------------------------------------------------
+/// How far a metric may move the wrong way before `fossil check`
+/// calls it a regression. The sign says which way is worse: `+5%`
+/// fails a metric that grows by more than 5%, such as a time, and
+/// `-2.5` one that drops by more than 2.5, such as a throughput.
+/// `+0%` fails on any significant worsening.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Threshold {
+    pub higher_is_worse: bool,
+    pub amount: f64,
+    pub relative: bool,
+}
+
+impl Threshold {
+    /// How much worse `after` is than `before`, in the threshold's
+    /// unit, negative when it improved. `None` for a percentage of
+    /// a zero baseline.
+    pub fn worsening(&self, before: f64, after: f64) -> Option<f64> {
+        let delta = if self.higher_is_worse {
+            after - before
+        } else {
+            before - after
+        };
+        if !self.relative {
+            return Some(delta);
+        }
+        (before != 0.0).then(|| delta / before.abs() * 100.0)
+    }
+
+    pub fn exceeded(&self, before: f64, after: f64) -> bool {
+        self.worsening(before, after)
+            .is_some_and(|w| w > self.amount)
+    }
+}
+
+impl FromStr for Threshold {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid threshold {s:?}, expected e.g. +5% (higher is \
+                 worse) or -2.5 (lower is worse)"
+            )
+        };
+        let s = s.trim();
+        let (higher_is_worse, rest) =
+            match (s.strip_prefix('+'), s.strip_prefix('-')) {
+                (Some(rest), _) => (true, rest),
+                (_, Some(rest)) => (false, rest),
+                _ => return Err(invalid()),
+            };
+        let (num, relative) = match rest.strip_suffix('%') {
+            Some(num) => (num, true),
+            None => (rest, false),
+        };
+        let amount: f64 = num.trim().parse().map_err(|_| invalid())?;
+        if !amount.is_finite() || amount < 0.0 {
+            return Err(invalid());
+        }
+        Ok(Self {
+            higher_is_worse,
+            amount,
+            relative,
+        })
+    }
+}
+
+impl fmt::Display for Threshold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.higher_is_worse { '+' } else { '-' };
+        let unit = if self.relative { "%" } else { "" };
+        write!(f, "{sign}{}{unit}", self.amount)
+    }
+}
+
+impl<'de> Deserialize<'de> for Threshold {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for Threshold {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// One variant's latest record and the baseline it is held to.
+pub struct CheckTarget {
+    pub variant: String,
+    pub latest: (String, Samples),
+    /// `None` when no record matches the baseline, e.g. a variant
+    /// added since; such variants are reported but not failed.
+    pub baseline: Option<(String, Samples)>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Verdict {
+    Pass,
+    Improved,
+    /// Past the threshold, but not significantly.
+    Noise,
+    Regressed,
+    Missing,
+}
+
+impl Verdict {
+    fn name(self) -> &'static str {
+        match self {
+            Verdict::Pass => "pass",
+            Verdict::Improved => "improved",
+            Verdict::Noise => "noise",
+            Verdict::Regressed => "FAIL",
+            Verdict::Missing => "missing",
+        }
+    }
+}
+
+struct Row {
+    metric: String,
+    threshold: Threshold,
+    before: Option<f64>,
+    after: Option<f64>,
+    p_value: Option<f64>,
+    verdict: Verdict,
+}
+
+/// [Fossil Doc] `Check`
+/// -------------------------------------------------------------
+/// The verdict of `fossil check`: every thresholded metric of each
+/// variant's latest record against its baseline. A metric fails when
+/// it moved the wrong way by more than its threshold and the move is
+/// significant at `alpha`. A metric either side lacks fails too, so
+/// a renamed metric can't slip through the gate unchecked.
+pub struct Check {
+    test: SignificanceTest,
+    alpha: f64,
+    blocks: Vec<(CheckTarget, Vec<Row>)>,
+}
+
+impl Check {
+    pub fn new(
+        targets: Vec<CheckTarget>,
+        thresholds: &BTreeMap<String, Threshold>,
+        test: SignificanceTest,
+        alpha: f64,
+    ) -> Self {
+        let blocks = targets
+            .into_iter()
+            .map(|target| {
+                let rows = match &target.baseline {
+                    None => Vec::new(),
+                    Some((_, base)) => thresholds
+                        .iter()
+                        .map(|(metric, &threshold)| {
+                            let a = base.get(metric);
+                            let b = target.latest.1.get(metric);
+                            Row::new(metric, threshold, a, b, test, alpha)
+                        })
+                        .collect(),
+                };
+                (target, rows)
+            })
+            .collect();
+        Self {
+            test,
+            alpha,
+            blocks,
+        }
+    }
+
+    /// Metrics that regressed or went missing, across all variants.
+    pub fn failures(&self) -> usize {
+        self.blocks
+            .iter()
+            .flat_map(|(_, rows)| rows)
+            .filter(|r| {
+                matches!(r.verdict, Verdict::Regressed | Verdict::Missing)
+            })
+            .count()
+    }
+}
+
+impl Row {
+    fn new(
+        metric: &str,
+        threshold: Threshold,
+        a: Option<&Vec<f64>>,
+        b: Option<&Vec<f64>>,
+        test: SignificanceTest,
+        alpha: f64,
+    ) -> Self {
+        let (before, after) =
+            (a.map(|xs| stats::mean(xs)), b.map(|xs| stats::mean(xs)));
+        let p_value = a.zip(b).and_then(|(a, b)| test.p_value(a, b));
+        let significant = p_value.is_some_and(|p| p < alpha);
+        let verdict = match (before, after) {
+            (Some(before), Some(after)) => {
+                let better = threshold
+                    .worsening(before, after)
+                    .is_some_and(|w| w < 0.0);
+                match (threshold.exceeded(before, after), significant) {
+                    (true, true) => Verdict::Regressed,
+                    (true, false) => Verdict::Noise,
+                    (false, true) if better => Verdict::Improved,
+                    _ => Verdict::Pass,
+                }
+            }
+            _ => Verdict::Missing,
+        };
+        Self {
+            metric: metric.to_string(),
+            threshold,
+            before,
+            after,
+            p_value,
+            verdict,
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dash = || "-".to_string();
+        for (i, (target, rows)) in self.blocks.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            let Some((base_id, _)) = &target.baseline else {
+                writeln!(
+                    f,
+                    "{}: {} has no baseline, skipped",
+                    target.variant, target.latest.0
+                )?;
+                continue;
+            };
+            writeln!(
+                f,
+                "{}: {} vs {base_id} ({}, alpha={})",
+                target.variant,
+                target.latest.0,
+                self.test.name(),
+                self.alpha
+            )?;
+
+            let header = [
+                "metric",
+                "baseline",
+                "latest",
+                "change",
+                "threshold",
+                "p",
+                "verdict",
+            ]
+            .map(String::from);
+            let table: Vec<[String; 7]> = rows
+                .iter()
+                .map(|row| {
+                    let change = match (row.before, row.after) {
+                        (Some(a), Some(b)) if row.threshold.relative => {
+                            if a == 0.0 {
+                                dash()
+                            } else {
+                                format!("{:+.2}%", (b - a) / a.abs() * 100.0)
+                            }
+                        }
+                        (Some(a), Some(b)) => format!("{:+.3}", b - a),
+                        _ => dash(),
+                    };
+                    [
+                        row.metric.clone(),
+                        row.before.map_or_else(dash, |x| format!("{x:.3}")),
+                        row.after.map_or_else(dash, |x| format!("{x:.3}")),
+                        change,
+                        row.threshold.to_string(),
+                        row.p_value.map_or_else(dash, |p| format!("{p:.4}")),
+                        row.verdict.name().to_string(),
+                    ]
+                })
+                .collect();
+
+            let mut align = [Align::Right; 7];
+            align[0] = Align::Left;
+            write_table(f, &header, &table, align)?;
+        }
+
+        let checked: usize =
+            self.blocks.iter().map(|(_, rows)| rows.len()).sum();
+        writeln!(f)?;
+        match self.failures() {
+            0 => writeln!(f, "passed: {checked} checks"),
+            n => writeln!(f, "failed: {n} of {checked} checks"),
+        }
+    }
+}
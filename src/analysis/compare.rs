@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::stats::{self, SignificanceTest};
+use super::table::{Align, write_table};
+
+/// Raw per-observation values of every numeric leaf, keyed by the
+/// dotted path into the analysis output (e.g. `phases.parse_ms`).
+pub type Samples = BTreeMap<String, Vec<f64>>;
+
+struct Cell {
+    mean: f64,
+    stddev: f64,
+    n: usize,
+}
+
+impl Cell {
+    fn of(xs: &[f64]) -> Self {
+        Self {
+            mean: stats::mean(xs),
+            stddev: stats::variance(xs).sqrt(),
+            n: xs.len(),
+        }
+    }
+}
+
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.3} ± {:.3} (n={})", self.mean, self.stddev, self.n)
+    }
+}
+
+struct Row {
+    metric: String,
+    baseline: Option<Cell>,
+    other: Option<Cell>,
+    p_value: Option<f64>,
+}
+
+/// [Fossil Doc] `Comparison`
+/// -------------------------------------------------------------
+/// Side-by-side view of every scalar metric across two or more
+/// columns. The first column is the baseline; every other column
+/// gets a delta, a percent change, and a significance verdict
+/// computed from the raw per-observation samples.
+pub struct Comparison {
+    test: SignificanceTest,
+    alpha: f64,
+    baseline: String,
+    blocks: Vec<(String, Vec<Row>)>,
+}
+
+impl Comparison {
+    pub fn new(
+        columns: &[(String, Samples)],
+        test: SignificanceTest,
+        alpha: f64,
+    ) -> Self {
+        let (baseline_label, baseline) = match columns.first() {
+            Some((l, s)) => (l.clone(), s),
+            None => {
+                return Self {
+                    test,
+                    alpha,
+                    baseline: String::new(),
+                    blocks: Vec::new(),
+                };
+            }
+        };
+
+        let blocks = columns[1..]
+            .iter()
+            .map(|(label, samples)| {
+                let metrics: std::collections::BTreeSet<&String> =
+                    baseline.keys().chain(samples.keys()).collect();
+                let rows = metrics
+                    .into_iter()
+                    .map(|metric| {
+                        let a = baseline.get(metric);
+                        let b = samples.get(metric);
+                        let p_value = match (a, b) {
+                            (Some(a), Some(b)) => test.p_value(a, b),
+                            _ => None,
+                        };
+                        Row {
+                            metric: metric.clone(),
+                            baseline: a.map(|xs| Cell::of(xs)),
+                            other: b.map(|xs| Cell::of(xs)),
+                            p_value,
+                        }
+                    })
+                    .collect();
+                (label.clone(), rows)
+            })
+            .collect();
+
+        Self {
+            test,
+            alpha,
+            baseline: baseline_label,
+            blocks,
+        }
+    }
+
+    fn verdict(&self, row: &Row) -> &'static str {
+        match (&row.baseline, &row.other, row.p_value) {
+            (None, _, _) | (_, None, _) => "missing",
+            (_, _, None) => "n/a",
+            (_, _, Some(p)) if p < self.alpha => "significant",
+            _ => "noise",
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dash = || "-".to_string();
+        for (i, (label, rows)) in self.blocks.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(
+                f,
+                "{} vs {label} ({}, alpha={})",
+                self.baseline,
+                self.test.name(),
+                self.alpha
+            )?;
+
+            let header = [
+                "metric".to_string(),
+                self.baseline.clone(),
+                label.clone(),
+                "delta".to_string(),
+                "change".to_string(),
+                "p".to_string(),
+                "verdict".to_string(),
+            ];
+            let table: Vec<[String; 7]> = rows
+                .iter()
+                .map(|row| {
+                    let (delta, change) = match (&row.baseline, &row.other) {
+                        (Some(a), Some(b)) => {
+                            let d = b.mean - a.mean;
+                            let pct = if a.mean == 0.0 {
+                                dash()
+                            } else {
+                                format!("{:+.2}%", d / a.mean.abs() * 100.0)
+                            };
+                            (format!("{d:+.3}"), pct)
+                        }
+                        _ => (dash(), dash()),
+                    };
+                    [
+                        row.metric.clone(),
+                        row.baseline
+                            .as_ref()
+                            .map_or_else(dash, |c| c.to_string()),
+                        row.other.as_ref().map_or_else(dash, |c| c.to_string()),
+                        delta,
+                        change,
+                        row.p_value.map_or_else(dash, |p| format!("{p:.4}")),
+                        self.verdict(row).to_string(),
+                    ]
+                })
+                .collect();
+
+            let mut align = [Align::Right; 7];
+            align[0] = Align::Left;
+            write_table(f, &header, &table, align)?;
+        }
+        Ok(())
+    }
+}
@@ -1,7 +1,17 @@
+mod cache;
+mod check;
+mod compare;
 pub mod quantity;
 mod script;
-pub use quantity::Metric;
-pub use script::AnalysisScript;
+pub mod stats;
+mod table;
+mod trend;
+pub use cache::ScriptCache;
+pub use check::{Check, CheckTarget, Threshold};
+pub use compare::{Comparison, Samples};
+pub use quantity::{Metric, SummaryField};
+pub use script::{AnalysisScript, ScriptOptions};
+pub use trend::{Trend, TrendOrder, TrendPoint, topo_position};
 
 use crate::error::FossilError;
 use std::collections::BTreeMap;
@@ -10,9 +20,12 @@ pub type AnalysisName = String;
 
 pub fn columns_to_json(
     columns: &[(String, Metric)],
+    fields: &[SummaryField],
 ) -> Result<String, FossilError> {
-    let map: BTreeMap<&str, &Metric> =
-        columns.iter().map(|(n, m)| (n.as_str(), m)).collect();
+    let map: BTreeMap<&str, serde_json::Value> = columns
+        .iter()
+        .map(|(n, m)| (n.as_str(), m.summarize(fields)))
+        .collect();
     serde_json::to_string_pretty(&map).map_err(|e| {
         FossilError::InvalidConfig(format!("serializing analysis: {e}"))
     })
@@ -1,20 +1,21 @@
-use serde::Serialize;
 use serde_json::Value;
 use std::collections::BTreeMap;
 
 use super::Quantity;
 use super::scalar::Scalar;
+use super::summary::SummaryField;
+use crate::analysis::Samples;
 
 /// [Fossil Doc] `Metric`
 /// -------------------------------------------------------------
 /// Recursive tree of analysis output, constructed from the shape of
 /// an analysis script output. By impl'ing the Quantity trait,
-/// Scalars get folded into mean+stddev, maps and lists recurse,
-/// tags pass through.
-#[derive(Clone, Serialize)]
-#[serde(untagged)]
+/// Scalars get folded into summary statistics, maps and lists
+/// recurse, tags pass through.
+#[derive(Clone)]
 pub enum Metric {
-    /// Numeric leaf value, folded into mean+stddev across observations.
+    /// Numeric leaf value, folded into summary statistics across
+    /// observations.
     Scalar(Scalar),
 
     /// Named sub-metrics, preserving the structure of the analysis JSON.
@@ -63,41 +64,83 @@ impl Metric {
             _ => Metric::Tag(String::new()),
         }
     }
+
+    /// Raw samples of every Scalar leaf, keyed by dotted path
+    /// (e.g. `phases.parse_ms`, `sizes.0`).
+    pub fn samples(&self) -> Samples {
+        let mut out = Samples::new();
+        self.collect_samples(String::new(), &mut out);
+        out
+    }
+
+    fn collect_samples(&self, path: String, out: &mut Samples) {
+        let join = |key: &str| {
+            if path.is_empty() {
+                key.to_string()
+            } else {
+                format!("{path}.{key}")
+            }
+        };
+        match self {
+            Metric::Scalar(s) => {
+                out.insert(path, s.samples().to_vec());
+            }
+            Metric::Map(map) => {
+                for (k, v) in map {
+                    v.collect_samples(join(k), out);
+                }
+            }
+            Metric::List(items) => {
+                for (i, v) in items.iter().enumerate() {
+                    v.collect_samples(join(&i.to_string()), out);
+                }
+            }
+            Metric::Tag(_) => {}
+        }
+    }
+
+    /// Render back into JSON with every Scalar replaced by the
+    /// requested summary statistics.
+    pub fn summarize(&self, fields: &[SummaryField]) -> Value {
+        match self {
+            Metric::Scalar(s) => s.summarize(fields),
+            Metric::Map(map) => Value::Object(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), v.summarize(fields)))
+                    .collect(),
+            ),
+            Metric::List(items) => Value::Array(
+                items.iter().map(|m| m.summarize(fields)).collect(),
+            ),
+            Metric::Tag(s) => Value::String(s.clone()),
+        }
+    }
 }
 
 impl Quantity for Metric {
     fn identity() -> Self {
         Metric::Map(BTreeMap::new())
     }
-    fn combine(&self, other: &Self) -> Self {
+    fn absorb(&mut self, other: &Self) {
         match (self, other) {
-            (Metric::Scalar(a), Metric::Scalar(b)) => {
-                Metric::Scalar(a.combine(b))
-            }
+            (Metric::Scalar(a), Metric::Scalar(b)) => a.absorb(b),
             (Metric::Map(a), Metric::Map(b)) => {
-                let mut map = a.clone();
                 for (k, v) in b {
-                    map.entry(k.clone())
-                        .and_modify(|e| *e = e.combine(v))
+                    a.entry(k.clone())
+                        .and_modify(|e| e.absorb(v))
                         .or_insert_with(|| v.clone());
                 }
-                Metric::Map(map)
             }
             (Metric::List(a), Metric::List(b)) => {
-                // Zip shared indices, then extend with the longer tail.
-                let mut out: Vec<Metric> = a
-                    .iter()
-                    .zip(b.iter())
-                    .map(|(x, y)| x.combine(y))
-                    .collect();
-                if a.len() > b.len() {
-                    out.extend_from_slice(&a[b.len()..]);
-                } else if b.len() > a.len() {
-                    out.extend_from_slice(&b[a.len()..]);
+                // Merge shared indices, then take the longer tail.
+                for (x, y) in a.iter_mut().zip(b) {
+                    x.absorb(y);
+                }
+                if b.len() > a.len() {
+                    a.extend_from_slice(&b[a.len()..]);
                 }
-                Metric::List(out)
             }
-            _ => self.clone(),
+            _ => {}
         }
     }
 }
@@ -1,15 +1,20 @@
 mod metric;
 mod scalar;
+mod summary;
 
 pub use metric::Metric;
+pub use summary::SummaryField;
 
 pub trait Quantity: Sized + Clone {
     fn identity() -> Self;
-    fn combine(&self, other: &Self) -> Self;
+    /// Merge `other` into `self` in place, so a fold doesn't copy its
+    /// accumulator at every step.
+    fn absorb(&mut self, other: &Self);
 }
 
 pub fn fold<Q: Quantity>(items: impl IntoIterator<Item = Q>) -> Q {
-    items
-        .into_iter()
-        .fold(Q::identity(), |acc, x| acc.combine(&x))
+    items.into_iter().fold(Q::identity(), |mut acc, x| {
+        acc.absorb(&x);
+        acc
+    })
 }
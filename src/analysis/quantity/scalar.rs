@@ -1,18 +1,22 @@
-use serde::Serialize;
-use serde::ser::SerializeMap;
+use serde_json::{Map, Value, json};
 
 use super::Quantity;
+use super::summary::SummaryField;
+use crate::analysis::stats;
 
 /// [Fossil Doc] `Scalar`
 /// -------------------------------------------------------------
 /// Online mean + variance via Welford's algorithm. Two Scalars
 /// can be merged without revisiting the original samples, so we
-/// can fold across iterations cheaply.
+/// can fold across iterations cheaply. The raw samples are kept
+/// alongside so order statistics (median, percentiles, min, max)
+/// survive the fold too.
 #[derive(Clone)]
 pub(crate) struct Scalar {
     n: usize,
     mean: f64,
     m2: f64,
+    samples: Vec<f64>,
 }
 
 impl Scalar {
@@ -21,19 +25,67 @@ impl Scalar {
             n: 1,
             mean: x,
             m2: 0.0,
+            samples: vec![x],
         }
     }
 
-    fn mean(&self) -> f64 {
+    pub fn samples(&self) -> &[f64] {
+        &self.samples
+    }
+
+    pub fn mean(&self) -> f64 {
         if self.n == 0 { 0.0 } else { self.mean }
     }
 
-    fn stddev(&self) -> f64 {
+    pub fn stddev(&self) -> f64 {
         if self.n < 2 {
             return 0.0;
         }
         (self.m2 / (self.n - 1) as f64).sqrt()
     }
+
+    /// Linearly interpolated quantile, `q` in [0, 1].
+    pub fn quantile(&self, q: f64) -> f64 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        let mut sorted = self.samples.clone();
+        sorted.sort_by(f64::total_cmp);
+        let pos = q.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+        let (lo, hi) = (pos.floor() as usize, pos.ceil() as usize);
+        sorted[lo] + (sorted[hi] - sorted[lo]) * (pos - lo as f64)
+    }
+
+    /// Student-t confidence interval of the mean at `level` (e.g. 0.95).
+    pub fn ci(&self, level: f64) -> (f64, f64) {
+        if self.n < 2 {
+            return (self.mean(), self.mean());
+        }
+        let t = stats::student_t_quantile(level, (self.n - 1) as f64);
+        let half = t * self.stddev() / (self.n as f64).sqrt();
+        (self.mean - half, self.mean + half)
+    }
+
+    pub fn summarize(&self, fields: &[SummaryField]) -> Value {
+        let mut map = Map::new();
+        for field in fields {
+            let value = match *field {
+                SummaryField::N => json!(self.n),
+                SummaryField::Mean => json!(self.mean()),
+                SummaryField::Stddev => json!(self.stddev()),
+                SummaryField::Median => json!(self.quantile(0.5)),
+                SummaryField::Min => json!(self.quantile(0.0)),
+                SummaryField::Max => json!(self.quantile(1.0)),
+                SummaryField::Percentile(q) => json!(self.quantile(q / 100.0)),
+                SummaryField::Ci(level) => {
+                    let (lo, hi) = self.ci(level / 100.0);
+                    json!([lo, hi])
+                }
+            };
+            map.insert(field.to_string(), value);
+        }
+        Value::Object(map)
+    }
 }
 
 impl Quantity for Scalar {
@@ -42,35 +94,25 @@ impl Quantity for Scalar {
             n: 0,
             mean: 0.0,
             m2: 0.0,
+            samples: Vec::new(),
         }
     }
 
     /// Welford's parallel merge for online mean + variance.
-    fn combine(&self, other: &Self) -> Self {
-        if self.n == 0 {
-            return other.clone();
-        }
+    fn absorb(&mut self, other: &Self) {
         if other.n == 0 {
-            return self.clone();
+            return;
+        }
+        if self.n == 0 {
+            *self = other.clone();
+            return;
         }
         let n = self.n + other.n;
         let delta = other.mean - self.mean;
-        let mean = self.mean + delta * other.n as f64 / n as f64;
-        let m2 = self.m2
-            + other.m2
+        self.mean += delta * other.n as f64 / n as f64;
+        self.m2 += other.m2
             + delta * delta * (self.n as f64 * other.n as f64) / n as f64;
-        Self { n, mean, m2 }
-    }
-}
-
-impl Serialize for Scalar {
-    fn serialize<S: serde::Serializer>(
-        &self,
-        serializer: S,
-    ) -> Result<S::Ok, S::Error> {
-        let mut map = serializer.serialize_map(Some(2))?;
-        map.serialize_entry("mean", &self.mean())?;
-        map.serialize_entry("stddev", &self.stddev())?;
-        map.end()
+        self.n = n;
+        self.samples.extend_from_slice(&other.samples);
     }
 }
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// [Fossil Doc] `SummaryField`
+/// -------------------------------------------------------------
+/// One statistic reported for every Scalar in analysis output.
+/// Spelled as in fossil.toml and `--summary`: `n`, `mean`,
+/// `stddev`, `median`, `min`, `max`, `p<q>` for any percentile
+/// (e.g. `p5`, `p99.9`), and `ci<level>` for a confidence interval
+/// of the mean (e.g. `ci95`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SummaryField {
+    N,
+    Mean,
+    Stddev,
+    Median,
+    Min,
+    Max,
+    Percentile(f64),
+    Ci(f64),
+}
+
+impl SummaryField {
+    pub const DEFAULT: &'static [SummaryField] =
+        &[SummaryField::Mean, SummaryField::Stddev];
+}
+
+impl FromStr for SummaryField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = |rest: &str| -> Result<f64, String> {
+            rest.parse::<f64>()
+                .ok()
+                .filter(|q| (0.0..=100.0).contains(q))
+                .ok_or_else(|| format!("invalid summary field {s:?}"))
+        };
+        match s {
+            "n" => Ok(Self::N),
+            "mean" => Ok(Self::Mean),
+            "stddev" => Ok(Self::Stddev),
+            "median" => Ok(Self::Median),
+            "min" => Ok(Self::Min),
+            "max" => Ok(Self::Max),
+            _ if s.starts_with("ci") => Ok(Self::Ci(number(&s[2..])?)),
+            _ if s.starts_with('p') => Ok(Self::Percentile(number(&s[1..])?)),
+            _ => Err(format!(
+                "invalid summary field {s:?}, expected one of: n, mean, \
+                 stddev, median, min, max, p<q>, ci<level>"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SummaryField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::N => f.write_str("n"),
+            Self::Mean => f.write_str("mean"),
+            Self::Stddev => f.write_str("stddev"),
+            Self::Median => f.write_str("median"),
+            Self::Min => f.write_str("min"),
+            Self::Max => f.write_str("max"),
+            Self::Percentile(q) => write!(f, "p{q}"),
+            Self::Ci(level) => write!(f, "ci{level}"),
+        }
+    }
+}
+
+impl Serialize for SummaryField {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for SummaryField {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
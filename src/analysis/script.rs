@@ -1,23 +1,59 @@
+use super::cache::ScriptCache;
 use super::quantity::{Metric, fold};
 use crate::error::FossilError;
 use crate::runner::{Observation, Results};
 use serde_json::Value;
 use std::fmt;
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 /// [Fossil Doc] `AnalysisScript`
 /// -------------------------------------------------------------
 /// A script that turns raw observations into structured metrics.
 /// Feeds each observation as JSON to the script's stdin, parses
-/// the JSON output, and folds across iterations.
+/// the JSON output, and folds across iterations. Observations run
+/// in parallel, up to `ScriptOptions::jobs` at once, and the parsed
+/// output is cached in the record, see `ScriptCache`.
 pub struct AnalysisScript {
     path: PathBuf,
+    options: ScriptOptions,
+}
+
+/// How an AnalysisScript walks a record's observations.
+#[derive(Debug, Default, Clone)]
+pub struct ScriptOptions {
+    /// Feed warmup iterations to the script too. Off by default,
+    /// since warmups exist precisely to be left out.
+    pub include_warmup: bool,
+    /// Run the script even where cached output is current, refreshing
+    /// the cache.
+    pub no_cache: bool,
+    /// Most script processes at once, one per core when unset.
+    pub jobs: Option<NonZeroUsize>,
+}
+
+impl ScriptOptions {
+    fn jobs(&self) -> usize {
+        self.jobs
+            .or_else(|| std::thread::available_parallelism().ok())
+            .map_or(1, NonZeroUsize::get)
+    }
 }
 
 impl AnalysisScript {
     pub fn new(path: PathBuf) -> Self {
-        Self { path }
+        Self {
+            path,
+            options: ScriptOptions::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: ScriptOptions) -> Self {
+        self.options = options;
+        self
     }
 
     fn fail(&self, reason: impl fmt::Display) -> FossilError {
@@ -58,21 +94,128 @@ impl AnalysisScript {
             .map_err(|e| self.fail(format_args!("invalid JSON output: {e}")))
     }
 
+    /// Run the script over every observation in a record, returning
+    /// the raw per-observation output in iteration order.
+    pub fn parse_all(&self, run_dir: &Path) -> Result<Vec<Value>, FossilError> {
+        let mut parsed = self.parse_records(&[run_dir.to_path_buf()])?;
+        Ok(parsed.pop().unwrap_or_default())
+    }
+
+    /// `parse_all` over several records at once. The observations of
+    /// every record without a current cache entry share one pool of
+    /// `jobs` workers, and come back in record then iteration order,
+    /// so the result is the same as running them one by one.
+    pub fn parse_records(
+        &self,
+        run_dirs: &[PathBuf],
+    ) -> Result<Vec<Vec<Value>>, FossilError> {
+        let mut cached = Vec::with_capacity(run_dirs.len());
+        let mut caches = Vec::with_capacity(run_dirs.len());
+        let mut work: Vec<(usize, Observation)> = Vec::new();
+        for (i, dir) in run_dirs.iter().enumerate() {
+            // Without a key, e.g. for a missing script, run uncached and
+            // let the script's own failure explain.
+            let cache =
+                ScriptCache::new(&self.path, dir, self.options.include_warmup)
+                    .ok();
+            let hit = match &cache {
+                Some(c) if !self.options.no_cache => c.load(),
+                _ => None,
+            };
+            if hit.is_none() {
+                let results = Results::load(dir)?;
+                let warmup = if self.options.include_warmup {
+                    results.warmup
+                } else {
+                    Vec::new()
+                };
+                work.extend(
+                    warmup
+                        .into_iter()
+                        .chain(results.observations)
+                        .map(|obs| (i, obs)),
+                );
+            }
+            cached.push(hit);
+            caches.push(cache);
+        }
+
+        let parsed =
+            try_map_ordered(&work, self.options.jobs(), |(_, obs)| {
+                self.parse(obs)
+            })?;
+        let mut fresh = vec![Vec::new(); run_dirs.len()];
+        for ((i, _), value) in work.iter().zip(parsed) {
+            fresh[*i].push(value);
+        }
+
+        let outputs = cached.into_iter().zip(fresh).zip(caches);
+        Ok(outputs
+            .map(|((hit, fresh), cache)| {
+                hit.unwrap_or_else(|| {
+                    if let Some(cache) = cache {
+                        // Only an optimization; a read-only record just
+                        // isn't cached.
+                        let _ = cache.store(&fresh);
+                    }
+                    fresh
+                })
+            })
+            .collect())
+    }
+
     pub fn collect(&self, run_dir: &Path) -> Result<Metric, FossilError> {
-        let raw = std::fs::read_to_string(run_dir.join("results.json"))?;
-        let results: Results = serde_json::from_str(&raw).map_err(|e| {
-            FossilError::InvalidConfig(format!(
-                "corrupt data in {}: {e}",
-                run_dir.display()
-            ))
-        })?;
-
-        let parsed: Vec<Value> = results
-            .observations
+        let parsed = self.parse_all(run_dir)?;
+        Ok(fold(parsed.iter().map(Metric::from_json)))
+    }
+
+    /// `collect` over several records, in the order given.
+    pub fn collect_records(
+        &self,
+        run_dirs: &[PathBuf],
+    ) -> Result<Vec<Metric>, FossilError> {
+        Ok(self
+            .parse_records(run_dirs)?
             .iter()
-            .map(|obs| self.parse(obs))
-            .collect::<Result<Vec<_>, _>>()?;
+            .map(|parsed| fold(parsed.iter().map(Metric::from_json)))
+            .collect())
+    }
+}
 
-        Ok(fold(parsed.into_iter().map(|v| Metric::from_json(&v))))
+/// `f` over `items` on up to `jobs` threads, each taking the next
+/// unclaimed item, with the results in the order of `items`. After a
+/// failure no more items are started, and the error returned is the
+/// one of the earliest failed item, as a serial run would.
+fn try_map_ordered<T: Sync, R: Send, E: Send>(
+    items: &[T],
+    jobs: usize,
+    f: impl Fn(&T) -> Result<R, E> + Sync,
+) -> Result<Vec<R>, E> {
+    if jobs <= 1 || items.len() <= 1 {
+        return items.iter().map(f).collect();
     }
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let slots: Vec<Mutex<Option<Result<R, E>>>> =
+        items.iter().map(|_| Mutex::new(None)).collect();
+    std::thread::scope(|s| {
+        for _ in 0..jobs.min(items.len()) {
+            s.spawn(|| {
+                while !failed.load(Ordering::Relaxed) {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(i) else { break };
+                    let result = f(item);
+                    if result.is_err() {
+                        failed.store(true, Ordering::Relaxed);
+                    }
+                    *slots[i].lock().unwrap() = Some(result);
+                }
+            });
+        }
+    });
+    // Items left unstarted sit after a failure, which is hit first.
+    slots
+        .into_iter()
+        .map_while(|slot| slot.into_inner().unwrap())
+        .collect()
 }
@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+
+/// [Fossil Doc] `SignificanceTest`
+/// -------------------------------------------------------------
+/// Two-sample hypothesis tests used to decide whether a difference
+/// between two sets of observations is real or just noise.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Deserialize, Serialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignificanceTest {
+    /// Welch's unequal-variance t-test. Assumes roughly normal samples.
+    Welch,
+    /// Mann-Whitney U test. Rank based, robust to skew and outliers.
+    MannWhitney,
+}
+
+impl SignificanceTest {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Welch => "welch",
+            Self::MannWhitney => "mann-whitney",
+        }
+    }
+
+    /// Two-sided p-value for the null hypothesis that `a` and `b`
+    /// come from the same distribution. `None` when either side has
+    /// too few samples for the test to say anything.
+    pub fn p_value(&self, a: &[f64], b: &[f64]) -> Option<f64> {
+        match self {
+            Self::Welch => welch_t_test(a, b),
+            Self::MannWhitney => mann_whitney_u(a, b),
+        }
+    }
+}
+
+pub fn mean(xs: &[f64]) -> f64 {
+    if xs.is_empty() {
+        return 0.0;
+    }
+    xs.iter().sum::<f64>() / xs.len() as f64
+}
+
+/// Unbiased sample variance.
+pub fn variance(xs: &[f64]) -> f64 {
+    if xs.len() < 2 {
+        return 0.0;
+    }
+    let m = mean(xs);
+    xs.iter().map(|x| (x - m) * (x - m)).sum::<f64>() / (xs.len() - 1) as f64
+}
+
+/// Half-width of the `level` (e.g. 0.95) confidence interval of the
+/// mean, as a fraction of the mean. `None` below two samples or for
+/// a zero mean, where it is undefined.
+pub fn relative_ci_half_width(xs: &[f64], level: f64) -> Option<f64> {
+    let m = mean(xs);
+    if xs.len() < 2 || m == 0.0 {
+        return None;
+    }
+    let t = student_t_quantile(level, (xs.len() - 1) as f64);
+    Some(t * variance(xs).sqrt() / (xs.len() as f64).sqrt() / m.abs())
+}
+
+pub fn welch_t_test(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.len() < 2 || b.len() < 2 {
+        return None;
+    }
+    let (na, nb) = (a.len() as f64, b.len() as f64);
+    let (va, vb) = (variance(a) / na, variance(b) / nb);
+    let diff = mean(a) - mean(b);
+    if va + vb == 0.0 {
+        // Both samples are constant: either identical or trivially
+        // different.
+        return Some(if diff == 0.0 { 1.0 } else { 0.0 });
+    }
+    let t = diff / (va + vb).sqrt();
+    let df = (va + vb).powi(2) / (va * va / (na - 1.0) + vb * vb / (nb - 1.0));
+    Some(student_t_two_sided(t, df))
+}
+
+pub fn mann_whitney_u(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.is_empty() || b.is_empty() || a.len() + b.len() < 3 {
+        return None;
+    }
+    let (na, nb) = (a.len() as f64, b.len() as f64);
+    let mut pooled: Vec<(f64, bool)> = a
+        .iter()
+        .map(|&x| (x, true))
+        .chain(b.iter().map(|&x| (x, false)))
+        .collect();
+    pooled.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+    // Assign average ranks to ties and accumulate the tie correction.
+    let mut rank_sum_a = 0.0;
+    let mut tie_term = 0.0;
+    let mut i = 0;
+    while i < pooled.len() {
+        let mut j = i;
+        while j + 1 < pooled.len() && pooled[j + 1].0 == pooled[i].0 {
+            j += 1;
+        }
+        let rank = (i + j) as f64 / 2.0 + 1.0;
+        for item in &pooled[i..=j] {
+            if item.1 {
+                rank_sum_a += rank;
+            }
+        }
+        let t = (j - i + 1) as f64;
+        tie_term += t * t * t - t;
+        i = j + 1;
+    }
+
+    let n = na + nb;
+    let u = rank_sum_a - na * (na + 1.0) / 2.0;
+    let mu = na * nb / 2.0;
+    let sigma =
+        (na * nb / 12.0 * ((n + 1.0) - tie_term / (n * (n - 1.0)))).sqrt();
+    if sigma == 0.0 {
+        return Some(1.0);
+    }
+    // Normal approximation with continuity correction.
+    let z = ((u - mu).abs() - 0.5).max(0.0) / sigma;
+    Some((2.0 * normal_sf(z)).min(1.0))
+}
+
+/// Two-sided critical value of Student's t for confidence `level`
+/// (e.g. 0.95), found by bisection on the CDF.
+pub fn student_t_quantile(level: f64, df: f64) -> f64 {
+    let alpha = 1.0 - level;
+    let (mut lo, mut hi) = (0.0, 1e3);
+    for _ in 0..100 {
+        let mid = (lo + hi) / 2.0;
+        if student_t_two_sided(mid, df) > alpha {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    (lo + hi) / 2.0
+}
+
+/// P(|T| >= |t|) for Student's t with `df` degrees of freedom.
+pub fn student_t_two_sided(t: f64, df: f64) -> f64 {
+    if !t.is_finite() {
+        return 0.0;
+    }
+    incomplete_beta(df / 2.0, 0.5, df / (df + t * t))
+}
+
+/// Upper tail of the standard normal distribution.
+pub fn normal_sf(z: f64) -> f64 {
+    0.5 * erfc(z / std::f64::consts::SQRT_2)
+}
+
+// Complementary error function, Chebyshev fit from Numerical Recipes
+// (fractional error < 1.2e-7 everywhere).
+fn erfc(x: f64) -> f64 {
+    const COEF: [f64; 10] = [
+        -1.265_512_23,
+        1.000_023_68,
+        0.374_091_96,
+        0.096_784_18,
+        -0.186_288_06,
+        0.278_868_07,
+        -1.135_203_98,
+        1.488_515_87,
+        -0.822_152_23,
+        0.170_872_77,
+    ];
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = COEF.iter().rev().fold(0.0, |acc, c| c + t * acc);
+    let r = t * (-z * z + poly).exp();
+    if x >= 0.0 { r } else { 2.0 - r }
+}
+
+fn ln_gamma(x: f64) -> f64 {
+    // Lanczos approximation, g = 7, n = 9.
+    const COEF: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let mut acc = COEF[0];
+    for (i, c) in COEF.iter().enumerate().skip(1) {
+        acc += c / (x + i as f64);
+    }
+    let t = x + 7.5;
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + acc.ln()
+}
+
+/// Regularized incomplete beta function I_x(a, b).
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b)
+        + a * x.ln()
+        + b * (1.0 - x).ln())
+    .exp();
+    // The continued fraction converges fastest below the mean; use the
+    // symmetry relation otherwise.
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_cf(a, b, x) / a
+    } else {
+        1.0 - front * beta_cf(b, a, 1.0 - x) / b
+    }
+}
+
+// Lentz's method for the incomplete beta continued fraction.
+fn beta_cf(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    const EPS: f64 = 1e-14;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..300 {
+        let m = m as f64;
+        let m2 = 2.0 * m;
+        let aa = m * (b - m) * x / ((a + m2 - 1.0) * (a + m2));
+        d = 1.0 + aa * d;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = 1.0 + aa / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        h *= d * c;
+        let aa = -(a + m) * (a + b + m) * x / ((a + m2) * (a + m2 + 1.0));
+        d = 1.0 + aa * d;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = 1.0 + aa / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        let del = d * c;
+        h *= del;
+        if (del - 1.0).abs() < EPS {
+            break;
+        }
+    }
+    h
+}
@@ -0,0 +1,35 @@
+use std::fmt;
+
+/// Which side of its column a cell sits on.
+#[derive(Clone, Copy)]
+pub(crate) enum Align {
+    Left,
+    Right,
+}
+
+/// Write `header` and `rows` as an indented plain-text table, each
+/// column as wide as its widest cell and aligned as `align` says.
+pub(crate) fn write_table<const N: usize>(
+    f: &mut fmt::Formatter<'_>,
+    header: &[String; N],
+    rows: &[[String; N]],
+    align: [Align; N],
+) -> fmt::Result {
+    let mut widths = header.clone().map(|h| h.chars().count());
+    for r in rows {
+        for (w, cell) in widths.iter_mut().zip(r) {
+            *w = (*w).max(cell.chars().count());
+        }
+    }
+    for r in std::iter::once(header).chain(rows) {
+        let mut line = String::new();
+        for ((cell, w), align) in r.iter().zip(widths).zip(align) {
+            match align {
+                Align::Left => line.push_str(&format!("  {cell:<w$}")),
+                Align::Right => line.push_str(&format!("  {cell:>w$}")),
+            }
+        }
+        writeln!(f, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
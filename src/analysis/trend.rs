@@ -0,0 +1,233 @@
+use std::fmt;
+use std::ops::Range;
+
+use super::stats::{self, SignificanceTest};
+use super::table::{Align, write_table};
+
+/// Confidence level of the band drawn around each record's mean.
+pub const BAND_LEVEL: f64 = 0.95;
+
+/// How `fossil trend` lines records up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum TrendOrder {
+    /// By bury timestamp.
+    #[default]
+    Time,
+    /// By the commit each record ran at, oldest ancestor first, as
+    /// `git rev-list --topo-order` lists them. Records at the same
+    /// commit keep their time order.
+    Commit,
+}
+
+/// Where a record at `sha` falls in `topo`, a listing from
+/// `GitInfo::topo_order`. Manifests without a full sha are matched on
+/// their short `commit`.
+pub fn topo_position(
+    topo: &[String],
+    sha: &str,
+    commit: &str,
+) -> Option<usize> {
+    topo.iter().position(|s| {
+        if sha.is_empty() {
+            !commit.is_empty() && s.starts_with(commit)
+        } else {
+            s == sha
+        }
+    })
+}
+
+/// One record's observations of the trended metric.
+pub struct TrendPoint {
+    pub record: String,
+    pub commit: String,
+    pub timestamp: String,
+    pub samples: Vec<f64>,
+}
+
+impl TrendPoint {
+    pub fn mean(&self) -> f64 {
+        stats::mean(&self.samples)
+    }
+
+    /// Half-width of the `BAND_LEVEL` confidence interval of the mean,
+    /// zero below two samples.
+    pub fn band(&self) -> f64 {
+        let n = self.samples.len();
+        if n < 2 {
+            return 0.0;
+        }
+        let t = stats::student_t_quantile(BAND_LEVEL, (n - 1) as f64);
+        t * stats::variance(&self.samples).sqrt() / (n as f64).sqrt()
+    }
+}
+
+/// A significant shift in the metric's distribution, between the
+/// records before `at` and the records from `at` on.
+pub struct Changepoint {
+    pub at: usize,
+    pub before: f64,
+    pub after: f64,
+    pub p_value: f64,
+}
+
+impl Changepoint {
+    pub fn change(&self) -> Option<f64> {
+        (self.before != 0.0)
+            .then(|| (self.after - self.before) / self.before.abs() * 100.0)
+    }
+}
+
+/// [Fossil Doc] `Trend`
+/// -------------------------------------------------------------
+/// One metric across a variant's records in order, each record's
+/// mean with a 95% band, and the points where it shifted. Shifts are
+/// found by binary segmentation: the split of a run of records whose
+/// pooled observations differ most significantly is kept if it beats
+/// `alpha` (Bonferroni-corrected for the splits tried), then both
+/// halves are searched again.
+pub struct Trend {
+    pub metric: String,
+    pub points: Vec<TrendPoint>,
+    pub changepoints: Vec<Changepoint>,
+    test: SignificanceTest,
+    alpha: f64,
+}
+
+impl Trend {
+    pub fn new(
+        metric: String,
+        points: Vec<TrendPoint>,
+        test: SignificanceTest,
+        alpha: f64,
+    ) -> Self {
+        let mut changepoints = Vec::new();
+        segment(&points, 0, points.len(), test, alpha, &mut changepoints);
+        changepoints.sort_by_key(|c| c.at);
+        // Report each shift between its neighbouring segments rather
+        // than the wider run it was found in.
+        let bounds: Vec<usize> = std::iter::once(0)
+            .chain(changepoints.iter().map(|c| c.at))
+            .chain(std::iter::once(points.len()))
+            .collect();
+        for (c, w) in changepoints.iter_mut().zip(bounds.windows(3)) {
+            c.before = stats::mean(&pool(&points, w[0]..w[1]));
+            c.after = stats::mean(&pool(&points, w[1]..w[2]));
+        }
+        Self {
+            metric,
+            points,
+            changepoints,
+            test,
+            alpha,
+        }
+    }
+}
+
+fn segment(
+    points: &[TrendPoint],
+    start: usize,
+    end: usize,
+    test: SignificanceTest,
+    alpha: f64,
+    out: &mut Vec<Changepoint>,
+) {
+    if end - start < 2 {
+        return;
+    }
+    let best = (start + 1..end)
+        .filter_map(|at| {
+            let (a, b) = (pool(points, start..at), pool(points, at..end));
+            let p = test.p_value(&a, &b)?;
+            Some((at, p, stats::mean(&a), stats::mean(&b)))
+        })
+        .min_by(|x, y| x.1.total_cmp(&y.1));
+    let Some((at, p_value, before, after)) = best else {
+        return;
+    };
+    let tried = (end - start - 1) as f64;
+    if p_value * tried >= alpha {
+        return;
+    }
+    out.push(Changepoint {
+        at,
+        before,
+        after,
+        p_value,
+    });
+    segment(points, start, at, test, alpha, out);
+    segment(points, at, end, test, alpha, out);
+}
+
+fn pool(points: &[TrendPoint], range: Range<usize>) -> Vec<f64> {
+    points[range]
+        .iter()
+        .flat_map(|p| p.samples.iter().copied())
+        .collect()
+}
+
+impl fmt::Display for Trend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} over {} records ({}, alpha={})",
+            self.metric,
+            self.points.len(),
+            self.test.name(),
+            self.alpha
+        )?;
+        let header =
+            ["record", "commit", "n", "mean", "ci95", ""].map(String::from);
+        let table: Vec<[String; 6]> = self
+            .points
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let shift = self
+                    .changepoints
+                    .iter()
+                    .find(|c| c.at == i)
+                    .map_or(String::new(), |c| {
+                        let arrow =
+                            if c.after > c.before { "▲" } else { "▼" };
+                        let pct = c
+                            .change()
+                            .map_or("-".into(), |x| format!("{x:+.1}%"));
+                        format!("{arrow} {pct}")
+                    });
+                [
+                    p.record.clone(),
+                    p.commit.clone(),
+                    p.samples.len().to_string(),
+                    format!("{:.3}", p.mean()),
+                    format!("±{:.3}", p.band()),
+                    shift,
+                ]
+            })
+            .collect();
+
+        let align = [
+            Align::Left,
+            Align::Left,
+            Align::Right,
+            Align::Right,
+            Align::Right,
+            Align::Left,
+        ];
+        write_table(f, &header, &table, align)?;
+
+        writeln!(f)?;
+        if self.changepoints.is_empty() {
+            return writeln!(f, "no significant shifts");
+        }
+        for c in &self.changepoints {
+            let first = &self.points[c.at];
+            let pct = c.change().map_or("-".into(), |x| format!("{x:+.2}%"));
+            writeln!(
+                f,
+                "shift at {} ({}): {:.3} -> {:.3} ({pct}, p={:.4})",
+                first.commit, first.timestamp, c.before, c.after, c.p_value
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,355 @@
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::FossilError;
+use crate::runner::{Progress, warn};
+
+/// A size in bytes, written in fossil.toml as a plain number or with
+/// a binary unit suffix: `"512K"`, `"64M"`, `"1.5G"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(transparent)]
+pub struct ByteSize(pub u64);
+
+impl ByteSize {
+    const UNITS: [(&'static str, u64); 4] =
+        [("G", 1 << 30), ("M", 1 << 20), ("K", 1 << 10), ("B", 1)];
+}
+
+impl FromStr for ByteSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let split = s
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(s.len());
+        let (num, unit) = s.split_at(split);
+        let unit = unit.trim().to_ascii_uppercase();
+        let unit = unit
+            .strip_suffix("IB")
+            .or_else(|| unit.strip_suffix('B').filter(|u| !u.is_empty()))
+            .unwrap_or(&unit);
+        let scale = match unit {
+            "" => 1,
+            u => Self::UNITS
+                .iter()
+                .find(|(name, _)| *name == u)
+                .map(|(_, scale)| *scale)
+                .ok_or_else(|| format!("unknown size unit in {s:?}"))?,
+        };
+        let n: f64 = num
+            .parse()
+            .map_err(|_| format!("invalid size {s:?}, expected e.g. 64M"))?;
+        Ok(Self((n * scale as f64) as u64))
+    }
+}
+
+impl std::fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (unit, scale) = Self::UNITS
+            .iter()
+            .find(|(_, scale)| self.0 >= *scale)
+            .unwrap_or(&("B", 1));
+        if *scale == 1 {
+            write!(f, "{}B", self.0)
+        } else {
+            write!(f, "{:.1}{unit}", self.0 as f64 / *scale as f64)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Bytes(u64),
+            Text(String),
+        }
+        match Repr::deserialize(deserializer)? {
+            Repr::Bytes(n) => Ok(Self(n)),
+            Repr::Text(s) => s.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// When an artifact pattern is collected: once after the last
+/// iteration, or after every measured iteration into its own
+/// directory.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Per {
+    #[default]
+    Run,
+    Iteration,
+}
+
+/// [Fossil Doc] `ArtifactConfig`
+/// -------------------------------------------------------------
+/// Files a benchmark leaves behind that are worth keeping with its
+/// record, matched by a glob relative to the fossil's workdir.
+/// Matches larger than `max_size`, or past `max_total` for the
+/// pattern, are skipped. Files over `commit_max_size`, or all of
+/// them with `commit = false`, are kept in the record directory but
+/// left out of the project's git history.
+///
+/// ```toml
+/// [[artifacts]]
+/// glob = "perf.data"
+/// per = "iteration"
+/// max_size = "512M"
+/// commit = false
+///
+/// [[artifacts]]
+/// glob = "reports/*.json"
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ArtifactConfig {
+    pub glob: String,
+    #[serde(default)]
+    pub per: Per,
+    #[serde(default = "ArtifactConfig::default_max_size")]
+    pub max_size: ByteSize,
+    #[serde(default = "ArtifactConfig::default_max_total")]
+    pub max_total: ByteSize,
+    #[serde(default = "ArtifactConfig::default_commit")]
+    pub commit: bool,
+    #[serde(default = "ArtifactConfig::default_commit_max_size")]
+    pub commit_max_size: ByteSize,
+}
+
+impl ArtifactConfig {
+    fn default_max_size() -> ByteSize {
+        ByteSize(64 << 20)
+    }
+
+    fn default_max_total() -> ByteSize {
+        ByteSize(256 << 20)
+    }
+
+    fn default_commit() -> bool {
+        true
+    }
+
+    fn default_commit_max_size() -> ByteSize {
+        ByteSize(10 << 20)
+    }
+}
+
+/// An artifact as listed in the manifest. `path` is relative to the
+/// record directory, `source` to the workdir it was collected from.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Artifact {
+    pub path: String,
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iteration: Option<u32>,
+    pub size: u64,
+    pub sha256: String,
+    pub committed: bool,
+}
+
+/// Bytes collected for one pattern so far, and whether it has
+/// matched anything or hit its `max_total`.
+#[derive(Debug, Default, Clone)]
+struct Tally {
+    bytes: u64,
+    matched: bool,
+    full: bool,
+}
+
+/// [Fossil Doc] `Artifacts`
+/// -------------------------------------------------------------
+/// Collects a run's artifacts into a staging directory under the
+/// records dir until the record exists, then moves them into its
+/// `artifacts/` directory. A run that fails before that leaves
+/// nothing behind.
+#[derive(Debug, Default)]
+pub struct Artifacts {
+    patterns: Vec<ArtifactConfig>,
+    staging: PathBuf,
+    tallies: Vec<Tally>,
+    pub collected: Vec<Artifact>,
+}
+
+impl Artifacts {
+    const DIR: &'static str = "artifacts";
+
+    pub fn new(patterns: Vec<ArtifactConfig>, staging: PathBuf) -> Self {
+        Self {
+            tallies: vec![Tally::default(); patterns.len()],
+            patterns,
+            staging,
+            collected: Vec::new(),
+        }
+    }
+
+    /// Copy whatever the patterns collected `per` match in `workdir`.
+    pub fn collect(
+        &mut self,
+        per: Per,
+        iteration: Option<u32>,
+        workdir: &Path,
+        progress: Option<&mpsc::Sender<Progress>>,
+    ) -> Result<(), FossilError> {
+        for (i, pattern) in self.patterns.iter().enumerate() {
+            if pattern.per != per {
+                continue;
+            }
+            let full = format!(
+                "{}/{}",
+                glob::Pattern::escape(&workdir.to_string_lossy()),
+                pattern.glob
+            );
+            let paths = glob::glob(&full).map_err(|e| {
+                FossilError::InvalidConfig(format!(
+                    "artifact glob {:?}: {e}",
+                    pattern.glob
+                ))
+            })?;
+            let tally = &mut self.tallies[i];
+            let mut matched = false;
+            for path in paths.filter_map(Result::ok).filter(|p| p.is_file()) {
+                matched = true;
+                let size = std::fs::metadata(&path)?.len();
+                let source = path
+                    .strip_prefix(workdir)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .into_owned();
+                if size > pattern.max_size.0 {
+                    warn(
+                        progress,
+                        format!(
+                            "skipping artifact {source} ({} > max_size {})",
+                            ByteSize(size),
+                            pattern.max_size
+                        ),
+                    );
+                    continue;
+                }
+                if tally.bytes + size > pattern.max_total.0 {
+                    if !tally.full {
+                        warn(
+                            progress,
+                            format!(
+                                "artifacts matching {:?} reached max_total \
+                                 {}, skipping the rest",
+                                pattern.glob, pattern.max_total
+                            ),
+                        );
+                        tally.full = true;
+                    }
+                    continue;
+                }
+                let rel = match iteration {
+                    Some(n) => Path::new(&n.to_string()).join(&source),
+                    None => PathBuf::from(&source),
+                };
+                let sha256 = copy_hashed(&path, &self.staging.join(&rel))?;
+                tally.bytes += size;
+                self.collected.push(Artifact {
+                    path: Path::new(Self::DIR)
+                        .join(&rel)
+                        .to_string_lossy()
+                        .into_owned(),
+                    source,
+                    iteration,
+                    size,
+                    sha256,
+                    committed: pattern.commit
+                        && size <= pattern.commit_max_size.0,
+                });
+            }
+            // Per-iteration patterns only get one chance to warn.
+            if !matched && !tally.matched && iteration.is_none_or(|n| n == 1) {
+                warn(
+                    progress,
+                    format!("artifact glob {:?} matched nothing", pattern.glob),
+                );
+            }
+            tally.matched |= matched;
+        }
+        Ok(())
+    }
+
+    /// Move the collected files into `run_dir`, writing a .gitignore
+    /// for those kept out of git. Returns the files to commit,
+    /// relative to `run_dir`.
+    pub fn store(
+        &mut self,
+        run_dir: &Path,
+    ) -> Result<Vec<PathBuf>, FossilError> {
+        if self.collected.is_empty() {
+            return Ok(Vec::new());
+        }
+        std::fs::rename(&self.staging, run_dir.join(Self::DIR))?;
+        let mut files: Vec<PathBuf> = self
+            .collected
+            .iter()
+            .filter(|a| a.committed)
+            .map(|a| PathBuf::from(&a.path))
+            .collect();
+        let ignored: Vec<String> = self
+            .collected
+            .iter()
+            .filter(|a| !a.committed)
+            .map(|a| format!("/{}\n", gitignore_escape(&a.path)))
+            .collect();
+        if !ignored.is_empty() {
+            std::fs::write(run_dir.join(".gitignore"), ignored.concat())?;
+            files.push(PathBuf::from(".gitignore"));
+        }
+        Ok(files)
+    }
+}
+
+impl Drop for Artifacts {
+    fn drop(&mut self) {
+        // Only still around if the run never made it to a record.
+        if self.staging.exists() {
+            let _ = std::fs::remove_dir_all(&self.staging);
+        }
+    }
+}
+
+/// Copy `from` to `to`, creating parent directories, and return the
+/// SHA-256 of the contents as hex.
+fn copy_hashed(from: &Path, to: &Path) -> Result<String, FossilError> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut src = std::fs::File::open(from)?;
+    let mut dst = std::fs::File::create(to)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = src.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        dst.write_all(&buf[..n])?;
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn gitignore_escape(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for c in path.chars() {
+        if matches!(c, '\\' | '*' | '?' | '[' | ' ' | '!' | '#') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
@@ -1,4 +1,9 @@
-use clap::{Parser, Subcommand};
+use crate::analysis::stats::SignificanceTest;
+use crate::analysis::{ScriptOptions, SummaryField, TrendOrder};
+use crate::environment::CpuSet;
+use crate::schedule::Order;
+use clap::{Args, Parser, Subcommand};
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -12,6 +17,31 @@ pub struct Cli {
     pub command: Option<Cmd>,
 }
 
+/// How analysis scripts run, shared by every command that runs them.
+#[derive(Args)]
+pub struct ScriptArgs {
+    #[arg(long, help = "Also count warmup iterations")]
+    pub include_warmup: bool,
+    #[arg(long, help = "Re-run analysis scripts, ignoring cached output")]
+    pub no_cache: bool,
+    #[arg(
+        short,
+        long,
+        help = "Most analysis scripts at once, default one per core"
+    )]
+    pub jobs: Option<NonZeroUsize>,
+}
+
+impl From<ScriptArgs> for ScriptOptions {
+    fn from(args: ScriptArgs) -> Self {
+        Self {
+            include_warmup: args.include_warmup,
+            no_cache: args.no_cache,
+            jobs: args.jobs,
+        }
+    }
+}
+
 #[derive(Subcommand)]
 pub enum Cmd {
     #[command(about = "Initialize the fossil home directory")]
@@ -34,8 +64,34 @@ pub enum Cmd {
         fossil: String,
         #[arg(short = 'n', long, help = "Number of iterations per variant")]
         iterations: Option<u32>,
+        #[arg(long, help = "Unrecorded warmup iterations before measuring")]
+        warmup: Option<u32>,
+        #[arg(
+            long,
+            help = "Iterate until the CI half-width is within this \
+                    fraction of the mean, e.g. 0.02"
+        )]
+        target_ci: Option<f64>,
+        #[arg(long, help = "Fewest iterations of an adaptive run")]
+        min_iterations: Option<u32>,
+        #[arg(long, help = "Most iterations of an adaptive run")]
+        max_iterations: Option<u32>,
+        #[arg(long, help = "Kill an iteration after this many seconds")]
+        timeout: Option<f64>,
+        #[arg(long, help = "Pin iterations to these cores, e.g. 2 or 0-3")]
+        cpus: Option<CpuSet>,
+        #[arg(
+            long,
+            value_enum,
+            help = "Interleaving when burying all variants"
+        )]
+        order: Option<Order>,
+        #[arg(long, help = "Seed for --order shuffle")]
+        seed: Option<u64>,
         #[arg(long, help = "Run a specific variant (omit to run all)")]
         variant: Option<String>,
+        #[arg(long = "tag", help = "Label the records, repeatable")]
+        tags: Vec<String>,
         #[arg(long, help = "Print the expanded command without running it")]
         dry_run: bool,
         #[arg(short, long, help = "Suppress subprocess output, show progress")]
@@ -51,6 +107,96 @@ pub enum Cmd {
         last: Option<usize>,
         #[arg(short, long, help = "Named analysis script")]
         analysis: Option<String>,
+        #[arg(
+            long,
+            value_delimiter = ',',
+            help = "Summary statistics, e.g. n,median,p5,p95,ci95"
+        )]
+        summary: Vec<SummaryField>,
+        #[command(flatten)]
+        script: ScriptArgs,
+    },
+    #[command(about = "Dig up a single record and its observations")]
+    Dig {
+        fossil: String,
+        #[arg(help = "Record id, variant:latest, or timestamp prefix")]
+        record: String,
+        #[arg(long, help = "Print full stdout/stderr of one iteration")]
+        observation: Option<u32>,
+        #[arg(long, help = "Output JSON instead of text")]
+        json: bool,
+    },
+    #[command(about = "Compare variants with significance testing")]
+    Compare {
+        fossil: String,
+        #[arg(
+            num_args = 2..,
+            required = true,
+            help = "Variants to compare, the first is the baseline"
+        )]
+        variants: Vec<String>,
+        #[arg(long, help = "Pool the last N records of each variant")]
+        last: Option<usize>,
+        #[arg(short, long, help = "Named analysis script")]
+        analysis: Option<String>,
+        #[command(flatten)]
+        script: ScriptArgs,
+        #[arg(long, value_enum, default_value = "welch")]
+        test: SignificanceTest,
+        #[arg(long, default_value_t = 0.05, help = "Significance level")]
+        alpha: f64,
+    },
+    #[command(about = "Fail when the latest records regressed from a baseline")]
+    #[command(group(
+        clap::ArgGroup::new("against")
+            .required(true)
+            .args(["baseline", "baseline_tag", "baseline_branch"]),
+    ))]
+    Check {
+        fossil: String,
+        #[arg(long, help = "Baseline record id, variant:latest or timestamp")]
+        baseline: Option<String>,
+        #[arg(
+            long,
+            help = "Baseline is the latest earlier record with this tag"
+        )]
+        baseline_tag: Option<String>,
+        #[arg(
+            long,
+            help = "Baseline is the latest earlier record on this branch"
+        )]
+        baseline_branch: Option<String>,
+        #[arg(long = "variant", help = "Variants to check (default all)")]
+        variants: Vec<String>,
+        #[arg(short, long, help = "Named analysis script")]
+        analysis: Option<String>,
+        #[command(flatten)]
+        script: ScriptArgs,
+        #[arg(long, value_enum, help = "Override [check] test")]
+        test: Option<SignificanceTest>,
+        #[arg(long, help = "Override [check] alpha")]
+        alpha: Option<f64>,
+    },
+    #[command(
+        about = "Show a metric across a variant's records and where it shifted"
+    )]
+    Trend {
+        #[arg(help = "fossil:variant")]
+        spec: String,
+        #[arg(long, help = "Metric to trend, may be omitted if there is one")]
+        metric: Option<String>,
+        #[arg(long, value_enum, default_value = "time")]
+        order: TrendOrder,
+        #[arg(long, help = "Only the last N records")]
+        last: Option<usize>,
+        #[arg(short, long, help = "Named analysis script")]
+        analysis: Option<String>,
+        #[command(flatten)]
+        script: ScriptArgs,
+        #[arg(long, value_enum, default_value = "welch")]
+        test: SignificanceTest,
+        #[arg(long, default_value_t = 0.05, help = "Significance level")]
+        alpha: f64,
     },
     #[command(about = "Render a figure from analyzed data")]
     Figure {
@@ -61,6 +207,13 @@ pub enum Cmd {
         variant: Option<String>,
         #[arg(long, help = "Named figure to render")]
         figure: Option<String>,
+        #[command(flatten)]
+        script: ScriptArgs,
+    },
+    #[command(about = "Manage cached analysis output")]
+    Cache {
+        #[command(subcommand)]
+        command: CacheCmd,
     },
     #[command(about = "List fossils in a project")]
     List,
@@ -71,6 +224,15 @@ pub enum Cmd {
     },
 }
 
+#[derive(Subcommand)]
+pub enum CacheCmd {
+    #[command(about = "Delete cached analysis output from records")]
+    Purge {
+        #[arg(help = "Only this fossil's records (default all)")]
+        fossil: Option<String>,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum ProjectCmd {
     #[command(about = "Create a new project")]
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+use std::process::Command as ProcessCommand;
+
+use crate::error::FossilError;
+
+/// [Fossil Doc] `CommandSpec`
+/// -------------------------------------------------------------
+/// The command a variant runs, in one of two forms:
+///
+/// ```toml
+/// shell = "gcc -O2 workload.c && ./a.out"   # run via `sh -c`
+/// argv = ["gcc", "-O2", "workload.c"]       # exec'd directly
+/// ```
+///
+/// The form is kept as-is in the manifest (a JSON string or array),
+/// so a rerun executes exactly the way the original did. An empty
+/// argv has nothing to exec and is rejected when loading.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum CommandSpec {
+    Shell(String),
+    Argv(Vec<String>),
+}
+
+impl CommandSpec {
+    /// Build a spec from trailing CLI arguments. A single argument is a
+    /// shell string (`-- "make && ./bench"`), several are an argv.
+    pub fn from_args(mut args: Vec<String>) -> Self {
+        if args.len() == 1 {
+            Self::Shell(args.remove(0))
+        } else {
+            Self::Argv(args)
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Shell(s) => s.trim().is_empty(),
+            Self::Argv(args) => args.is_empty(),
+        }
+    }
+
+    /// Apply `f` to the shell string, or to every argv element.
+    pub fn map(&self, f: impl Fn(&str) -> String) -> Self {
+        match self {
+            Self::Shell(s) => Self::Shell(f(s)),
+            Self::Argv(args) => Self::Argv(args.iter().map(|a| f(a)).collect()),
+        }
+    }
+
+    pub fn to_process(&self) -> Result<ProcessCommand, FossilError> {
+        match self {
+            Self::Shell(s) => {
+                let mut cmd = ProcessCommand::new("sh");
+                cmd.args(["-c", s]);
+                Ok(cmd)
+            }
+            Self::Argv(args) => {
+                let (program, rest) = args.split_first().ok_or_else(|| {
+                    FossilError::InvalidConfig("empty argv command".into())
+                })?;
+                let mut cmd = ProcessCommand::new(program);
+                cmd.args(rest);
+                Ok(cmd)
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CommandSpec {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Shell(String),
+            Argv(Vec<String>),
+        }
+        match Repr::deserialize(deserializer)? {
+            Repr::Shell(s) => Ok(Self::Shell(s)),
+            Repr::Argv(args) if args.is_empty() => {
+                Err(serde::de::Error::custom("argv command must not be empty"))
+            }
+            Repr::Argv(args) => Ok(Self::Argv(args)),
+        }
+    }
+}
+
+/// Shell strings print verbatim; argv prints as a copy-pasteable,
+/// shell-quoted line.
+impl fmt::Display for CommandSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Shell(s) => f.write_str(s),
+            Self::Argv(args) => {
+                let quoted: Vec<Cow<str>> =
+                    args.iter().map(|a| shell_quote(a)).collect();
+                f.write_str(&quoted.join(" "))
+            }
+        }
+    }
+}
+
+fn shell_quote(arg: &str) -> Cow<'_, str> {
+    let safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if safe {
+        Cow::Borrowed(arg)
+    } else {
+        Cow::Owned(format!("'{}'", arg.replace('\'', r"'\''")))
+    }
+}
+
+/// Substitute `$NAME` and `${NAME}` from the process environment.
+/// Unset variables expand to nothing, as in `sh`. Used for argv
+/// commands, which never pass through a shell.
+pub fn expand_env(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        let (name, consumed) = if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            }
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], end)
+        };
+        if name.is_empty() {
+            out.push('$');
+            rest = after;
+        } else {
+            out.push_str(&std::env::var(name).unwrap_or_default());
+            rest = &after[consumed..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
@@ -1,75 +1,468 @@
 use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc;
+use std::time::Duration;
 
-use crate::analysis::{self, quantity::Quantity};
+use crate::analysis::quantity::{self, Quantity};
+use crate::analysis::stats::SignificanceTest;
+use crate::analysis::{
+    self, Check, CheckTarget, Metric, Samples, ScriptOptions, TrendOrder,
+    TrendPoint, stats,
+};
+use crate::artifact::{Artifacts, ByteSize, Per};
 use crate::entity::DirEntity;
-use crate::environment::{CpuInfo, GitInfo};
+use crate::environment::{CpuInfo, CpuSet, GitInfo, MachineInfo, RunEnv};
 use crate::error::FossilError;
-use crate::fossil::{Fossil, FossilVariantKey};
-use crate::io::status;
+use crate::fossil::{
+    AdaptiveConfig, Fossil, FossilVariantKey, ResolvedVariant,
+};
+use crate::io::{status, warning};
 use crate::manifest::Manifest;
 use crate::project::Project;
 use crate::record::Record;
-use crate::runner::Run;
+use crate::runner::{
+    self, Convergence, HookKind, Observation, Progress, Results, Run,
+    StopReason,
+};
+use crate::schedule::{Order, Schedule};
+
+/// Per-invocation knobs for `bury`, gathered from the CLI or the TUI.
+/// Anything left unset falls back to the fossil's config.
+#[derive(Debug, Default, Clone)]
+pub struct BuryOptions {
+    /// A fixed iteration count, which also turns adaptive mode off.
+    pub iterations: Option<u32>,
+    pub warmup: Option<u32>,
+    /// Any of these turns adaptive mode on, overriding the fossil's
+    /// `[adaptive]` settings.
+    pub target_ci: Option<f64>,
+    pub min_iterations: Option<u32>,
+    pub max_iterations: Option<u32>,
+    /// Per-iteration timeout in seconds, overriding fossil.toml.
+    pub timeout: Option<f64>,
+    /// Cores to pin to, overriding fossil.toml and `BENCH_CPU`.
+    pub cpus: Option<CpuSet>,
+    /// How `bury_all` interleaves variants, overriding fossil.toml.
+    pub order: Option<Order>,
+    /// Seed for `Order::Shuffle`, overriding fossil.toml.
+    pub seed: Option<u64>,
+    /// Recorded in each manifest's `tags`.
+    pub tags: Vec<String>,
+    pub silent: bool,
+    /// Receives live progress, for front ends like the TUI. Replaces
+    /// the one-line progress of `silent` mode, and takes the bury's
+    /// warnings instead of stderr.
+    pub progress: Option<mpsc::Sender<Progress>>,
+    /// Set to kill the running iteration and abandon the bury.
+    pub cancel: Option<Arc<AtomicBool>>,
+}
 
 pub fn bury(
     fossil: &Fossil,
     project: &Project,
-    iterations: Option<u32>,
-    variant: Option<FossilVariantKey>,
-    command: String,
-    silent: bool,
+    variant: ResolvedVariant,
+    opts: &BuryOptions,
 ) -> Result<String, FossilError> {
-    if command.is_empty() {
+    let mut pending = prepare(fossil, project, variant, opts)?;
+    let measured = measure(fossil, &mut pending);
+    // Teardown runs even after a failure, and only ever warns; the
+    // measurement's own error comes first regardless.
+    let teardown = pending.run.hook(HookKind::Teardown, 0, false);
+    measured?;
+    teardown?;
+    finish(fossil, project, pending, None)
+}
+
+/// Setup, warmups, then the measured iterations of a single variant,
+/// and finally its per-run artifacts.
+fn measure(
+    fossil: &Fossil,
+    pending: &mut PendingBury,
+) -> Result<(), FossilError> {
+    pending.run.hook(HookKind::Setup, 0, false)?;
+    for _ in 0..pending.run.warmup {
+        step(fossil, &mut pending.run, true)?;
+    }
+    match pending.adaptive.take() {
+        Some(adaptive) => iterate_adaptively(fossil, pending, adaptive)?,
+        None => {
+            for _ in 0..pending.run.iterations {
+                step(fossil, &mut pending.run, false)?;
+            }
+        }
+    }
+    pending.run.collect_artifacts(Per::Run, None)
+}
+
+pub fn bury_all(
+    fossil: &Fossil,
+    project: &Project,
+    opts: &BuryOptions,
+) -> Result<(), FossilError> {
+    let variants = fossil.config.variant_keys();
+    if variants.is_empty() {
         return Err(FossilError::InvalidArgs(
-            "no command given — usage: fossil bury <name> -- <cmd...>".into(),
+            "no variants configured — define variants in fossil.toml or use -- <cmd>".into(),
         ));
     }
+    let order = opts.order.unwrap_or(fossil.config.order);
+    if order == Order::Sequential {
+        for vname in &variants {
+            let v = fossil.resolve_variant(vname, &project.config.constants)?;
+            bury(fossil, project, v, opts)?;
+        }
+        return Ok(());
+    }
 
-    let n = iterations.unwrap_or(fossil.config.default_iterations);
-    let mut run = Run {
-        command,
-        iterations: n,
-        variant,
+    let mut pending = variants
+        .iter()
+        .map(|vname| {
+            let v = fossil.resolve_variant(vname, &project.config.constants)?;
+            prepare(fossil, project, v, opts)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if pending.iter().any(|p| p.adaptive.is_some()) {
+        return Err(FossilError::InvalidArgs(
+            "adaptive iteration counts can't be scheduled ahead, use \
+             --order sequential or a fixed --iterations"
+                .into(),
+        ));
+    }
+    let iterations: Vec<u32> =
+        pending.iter().map(|p| p.run.iterations).collect();
+    let schedule = Schedule::new(
+        order,
+        opts.seed.or(fossil.config.seed),
+        variants,
+        &iterations,
+    );
+    let measured = measure_interleaved(fossil, &mut pending, &schedule);
+    let teardown: Result<(), FossilError> = pending
+        .iter_mut()
+        .try_for_each(|p| p.run.hook(HookKind::Teardown, 0, false));
+    measured?;
+    teardown?;
+    for p in pending {
+        finish(fossil, project, p, Some(schedule.clone()))?;
+    }
+    Ok(())
+}
+
+/// Every variant's setup and warmups, then the measured iterations
+/// in schedule order.
+fn measure_interleaved(
+    fossil: &Fossil,
+    pending: &mut [PendingBury],
+    schedule: &Schedule,
+) -> Result<(), FossilError> {
+    for p in pending.iter_mut() {
+        p.run.hook(HookKind::Setup, 0, false)?;
+    }
+    for p in pending.iter_mut() {
+        for _ in 0..p.run.warmup {
+            step(fossil, &mut p.run, true)?;
+        }
+    }
+    for &i in &schedule.sequence {
+        step(fossil, &mut pending[i].run, false)?;
+    }
+    for p in pending.iter_mut() {
+        p.run.collect_artifacts(Per::Run, None)?;
+    }
+    Ok(())
+}
+
+/// A Run plus what was captured about the source and machine before
+/// its first iteration. Becomes a record once finished.
+struct PendingBury {
+    run: Run,
+    adaptive: Option<AdaptiveConfig>,
+    git: GitInfo,
+    patch: Option<String>,
+    cpu: CpuInfo,
+    machine: MachineInfo,
+}
+
+fn prepare(
+    fossil: &Fossil,
+    project: &Project,
+    variant: ResolvedVariant,
+    opts: &BuryOptions,
+) -> Result<PendingBury, FossilError> {
+    if variant.command.is_empty() {
+        return Err(FossilError::InvalidArgs(
+            "no command given — usage: fossil bury <name> -- <cmd...>".into(),
+        ));
+    }
+    let silent = opts.silent;
+    let label = variant.label().to_string();
+    let n = opts
+        .iterations
+        .unwrap_or(fossil.config.default_iterations);
+    let warmup = opts.warmup.unwrap_or(fossil.config.warmup);
+    let adaptive = resolve_adaptive(fossil, opts)?;
+    let timeout = opts
+        .timeout
+        .or(variant.timeout)
+        .or(fossil.config.timeout)
+        .map(|secs| {
+            Duration::try_from_secs_f64(secs).map_err(|_| {
+                FossilError::InvalidConfig(format!("invalid timeout {secs}"))
+            })
+        })
+        .transpose()?;
+    let cpus = match opts.cpus.clone().or(fossil.config.cpus.clone()) {
+        Some(cpus) => Some(cpus),
+        None => CpuInfo::bench_cpu()?,
+    };
+    if let Some(ref cpus) = cpus {
+        cpus.validate()?;
+    }
+    let mut env =
+        fossil.expand_env(&fossil.config.env, &project.config.constants);
+    env.extend(variant.env);
+    let env = RunEnv::resolve(
+        env,
+        fossil.config.clear_env,
+        &fossil.config.keep_env(),
+    )?;
+    let cpu = CpuInfo::current(cpus.as_ref());
+    let machine = MachineInfo::current();
+    let (git, patch) = match &fossil.config.source {
+        Some(src) => {
+            let dir = src.resolve(&fossil.path);
+            let git = GitInfo::source(&dir)?;
+            let patch = git.dirty.then(|| GitInfo::diff(&dir));
+            if git.dirty {
+                runner::warn(
+                    opts.progress.as_ref(),
+                    format!(
+                        "source {} has uncommitted changes, saving them to \
+                         source.patch",
+                        dir.display()
+                    ),
+                );
+            }
+            (git, patch)
+        }
+        None => (GitInfo::current(&project.path), None),
+    };
+    if cpu.pinned {
+        for w in cpu.noise_warnings() {
+            runner::warn(opts.progress.as_ref(), w);
+        }
+    }
+    let run = Run {
+        command: variant.command,
+        iterations: adaptive.as_ref().map_or(n, |a| a.max_iterations),
+        warmup,
+        variant: variant.name,
+        params: variant.params,
+        hooks: variant.hooks,
         allow_failure: fossil.config.allow_failure,
         workdir: fossil
             .config
             .workdir
             .as_ref()
             .map(|p| p.resolve(&fossil.path)),
+        env,
+        tags: opts.tags.clone(),
+        timeout,
+        cpus,
         silent,
+        progress: opts.progress.clone(),
+        cancel: opts.cancel.clone(),
+        convergence: None,
+        warmups: Vec::new(),
         observations: Vec::new(),
+        hook_runs: Vec::new(),
+        artifacts: Artifacts::new(
+            fossil.config.artifacts.clone(),
+            fossil.records_dir().join(format!(
+                ".staging-{}-{}",
+                std::process::id(),
+                label
+            )),
+        ),
     };
 
-    let vname: String = run
-        .variant
-        .as_ref()
-        .map(|v| v.as_str().to_string())
-        .unwrap_or_else(|| "untagged".to_string());
-
-    for _ in 0..n {
-        if silent {
-            eprint!(
-                "\r[fossil] burying {}/{} ({}/{}) …",
-                fossil.config.name,
-                vname,
-                run.observations.len() + 1,
-                n,
-            );
-        } else {
+    Ok(PendingBury {
+        run,
+        adaptive,
+        git,
+        patch,
+        cpu,
+        machine,
+    })
+}
+
+/// Adaptive settings for this bury, if any: the fossil's `[adaptive]`
+/// table with CLI overrides on top. A fixed `--iterations` wins.
+fn resolve_adaptive(
+    fossil: &Fossil,
+    opts: &BuryOptions,
+) -> Result<Option<AdaptiveConfig>, FossilError> {
+    let requested = opts.target_ci.is_some()
+        || opts.min_iterations.is_some()
+        || opts.max_iterations.is_some();
+    if opts.iterations.is_some()
+        || (fossil.config.adaptive.is_none() && !requested)
+    {
+        return Ok(None);
+    }
+    let mut adaptive = fossil.config.adaptive.clone().unwrap_or_default();
+    if let Some(target) = opts.target_ci {
+        adaptive.target = target;
+    }
+    if let Some(min) = opts.min_iterations {
+        adaptive.min_iterations = min;
+    }
+    if let Some(max) = opts.max_iterations {
+        adaptive.max_iterations = max;
+    }
+    adaptive.validate()?;
+    Ok(Some(adaptive))
+}
+
+/// Run measured iterations until the CI of the adaptive metric is
+/// narrow enough or `max_iterations` is reached.
+fn iterate_adaptively(
+    fossil: &Fossil,
+    pending: &mut PendingBury,
+    adaptive: AdaptiveConfig,
+) -> Result<(), FossilError> {
+    let script = if adaptive.uses_analysis() {
+        Some(fossil.resolve_analysis(adaptive.analysis.as_deref())?)
+    } else {
+        None
+    };
+    let run = &mut pending.run;
+    let level = adaptive.confidence / 100.0;
+    let mut values = Vec::new();
+    let (stop, achieved) = loop {
+        step(fossil, run, false)?;
+        let obs = run.observations.last().unwrap();
+        values.push(match &script {
+            None => obs.wall_time_us as f64 / 1000.0,
+            Some(s) => {
+                let samples = Metric::from_json(&s.parse(obs)?).samples();
+                let value = samples
+                    .get(&adaptive.metric)
+                    .and_then(|v| v.first().copied());
+                value.ok_or_else(|| {
+                    let names: Vec<&str> =
+                        samples.keys().map(String::as_str).collect();
+                    FossilError::unknown("metric", &adaptive.metric, &names)
+                })?
+            }
+        });
+        let achieved = stats::relative_ci_half_width(&values, level);
+        if let Some(a) = achieved
+            && !run.silent
+        {
             status!(
-                "burying {}/{} ({}/{})",
-                fossil.config.name,
-                vname,
-                run.observations.len() + 1,
-                n,
+                "{} ±{:.2}% (target ±{:.2}%)",
+                adaptive.metric,
+                a * 100.0,
+                adaptive.target * 100.0
             );
         }
-        let obs = run.execute_one()?;
-        if !silent {
-            status!("{}ms", obs.wall_time_us / 1000);
+        let n = values.len() as u32;
+        if n >= adaptive.min_iterations
+            && achieved.is_some_and(|a| a <= adaptive.target)
+        {
+            break (StopReason::Converged, achieved);
         }
+        if n >= adaptive.max_iterations {
+            break (StopReason::MaxIterations, achieved);
+        }
+    };
+    run.iterations = run.observations.len() as u32;
+    run.convergence = Some(Convergence {
+        metric: adaptive.metric,
+        confidence: adaptive.confidence,
+        target: adaptive.target,
+        achieved,
+        stop,
+    });
+    Ok(())
+}
+
+/// Run one warmup or measured iteration, reporting progress.
+fn step(
+    fossil: &Fossil,
+    run: &mut Run,
+    warmup: bool,
+) -> Result<(), FossilError> {
+    let silent = run.silent;
+    let listened = run.progress.is_some();
+    let vname = run.label();
+    let (verb, done, total) = if warmup {
+        ("warming up", run.warmups.len(), run.warmup)
+    } else {
+        ("burying", run.observations.len(), run.iterations)
+    };
+    run.report(Progress::Started {
+        variant: vname.to_string(),
+        warmup,
+        iteration: done as u32 + 1,
+        total,
+    });
+    if listened {
+        // The listener shows progress instead.
+    } else if silent {
+        eprint!(
+            "\r[fossil] {verb} {}/{vname} ({}/{total}) …",
+            fossil.config.name,
+            done + 1,
+        );
+    } else {
+        status!(
+            "{verb} {}/{vname} ({}/{total})",
+            fossil.config.name,
+            done + 1
+        );
     }
-    if silent {
+    let obs = if warmup {
+        run.execute_warmup()?
+    } else {
+        run.execute_one()?
+    };
+    if !silent {
+        let suffix = if warmup { " (warmup)" } else { "" };
+        status!("{}ms{suffix}", obs.wall_time_us / 1000);
+    }
+    let event = Progress::Finished {
+        warmup,
+        iteration: obs.iteration,
+        wall_time_us: obs.wall_time_us,
+        outcome: obs.outcome(),
+    };
+    run.report(event);
+    Ok(())
+}
+
+/// Write the record for a completed Run and commit it.
+fn finish(
+    fossil: &Fossil,
+    project: &Project,
+    pending: PendingBury,
+    schedule: Option<Schedule>,
+) -> Result<String, FossilError> {
+    let PendingBury {
+        mut run,
+        git,
+        patch,
+        cpu,
+        machine,
+        ..
+    } = pending;
+    let n = run.iterations;
+    let silent = run.silent;
+    let vname = run.variant.as_ref().map_or("untagged", |v| v.as_str());
+    if silent && run.progress.is_none() {
         let avg_us: u64 = run
             .observations
             .iter()
@@ -83,14 +476,12 @@ pub fn bury(
         );
     }
 
-    let m = Manifest::new(
-        fossil,
-        project,
-        &run,
-        GitInfo::current(&project.path),
-        CpuInfo::current(),
-    );
-    let run_dir = m.record(&fossil.records_dir(), &run.results())?;
+    let m = Manifest {
+        schedule,
+        ..Manifest::new(fossil, project, &run, git, cpu, machine)?
+    };
+    let run_dir =
+        m.record(&fossil.records_dir(), &run.results(), patch.as_deref())?;
 
     let rel = run_dir
         .strip_prefix(&project.path)
@@ -101,15 +492,17 @@ pub fn bury(
             ))
         })?
         .to_path_buf();
-    let vname = run.variant.as_ref().map(FossilVariantKey::as_str);
-    project.commit(
-        vec![rel.join("manifest.json"), rel.join("results.json")],
-        format!(
-            "bury {} {}",
-            fossil.config.name,
-            vname.unwrap_or("untagged"),
-        ),
-    )?;
+    let mut files = vec![rel.join("manifest.json"), rel.join("results.json")];
+    if patch.is_some() {
+        files.push(rel.join("source.patch"));
+    }
+    files.extend(
+        run.artifacts
+            .store(&run_dir)?
+            .into_iter()
+            .map(|f| rel.join(f)),
+    );
+    project.commit(files, format!("bury {} {}", fossil.config.name, vname))?;
 
     let avg_ms = if run.observations.is_empty() {
         0
@@ -125,25 +518,6 @@ pub fn bury(
     Ok(format!("{n} observations recorded ({avg_ms}ms avg)"))
 }
 
-pub fn bury_all(
-    fossil: &Fossil,
-    project: &Project,
-    iterations: Option<u32>,
-    silent: bool,
-) -> Result<(), FossilError> {
-    let variants: Vec<_> = fossil.config.variants.keys().cloned().collect();
-    if variants.is_empty() {
-        return Err(FossilError::InvalidArgs(
-            "no variants configured — define variants in fossil.toml or use -- <cmd>".into(),
-        ));
-    }
-    for vname in &variants {
-        let v = fossil.resolve_variant(vname, &project.config.constants)?;
-        bury(fossil, project, iterations, Some(v.name), v.command, silent)?;
-    }
-    Ok(())
-}
-
 pub fn list_fossil_info(project: &Project) -> Result<(), FossilError> {
     let fossils = Fossil::list_all(project.fossils_dir())?;
     if fossils.is_empty() {
@@ -160,6 +534,7 @@ fn resolve_spec(
     spec: &str,
     last: Option<usize>,
     analysis: Option<&str>,
+    options: &ScriptOptions,
 ) -> Result<Vec<(String, analysis::Metric)>, FossilError> {
     let (fossil_name, variant) = match spec.split_once(':') {
         Some((f, v)) => (f, Some(v)),
@@ -167,7 +542,9 @@ fn resolve_spec(
     };
 
     let fossil = Fossil::load(&project.fossils_dir().join(fossil_name))?;
-    let script = fossil.resolve_analysis(analysis)?;
+    let script = fossil
+        .resolve_analysis(analysis)?
+        .with_options(options.clone());
 
     if let Some(vname) = variant {
         let records =
@@ -177,16 +554,19 @@ fn resolve_spec(
                 "no matching records found".into(),
             ));
         }
-        let mut cols = Vec::new();
-        for r in &records {
-            let metrics = script.collect(&r.dir)?;
-            let label = if records.len() == 1 {
-                vname.to_string()
-            } else {
-                r.id()
-            };
-            cols.push((label, metrics));
-        }
+        let metrics = script.collect_records(&dirs(&records))?;
+        let cols = records
+            .iter()
+            .zip(metrics)
+            .map(|(r, metrics)| {
+                let label = if records.len() == 1 {
+                    vname.to_string()
+                } else {
+                    r.id()
+                };
+                (label, metrics)
+            })
+            .collect();
         return Ok(cols);
     }
 
@@ -196,17 +576,20 @@ fn resolve_spec(
     }
 
     if last.is_some() {
-        let mut cols = Vec::new();
-        for r in &all {
-            let metrics = script.collect(&r.dir)?;
-            let label = r
-                .manifest
-                .variant
-                .as_ref()
-                .map(|v| v.to_string())
-                .unwrap_or_else(|| r.id());
-            cols.push((label, metrics));
-        }
+        let metrics = script.collect_records(&dirs(&all))?;
+        let cols = all
+            .iter()
+            .zip(metrics)
+            .map(|(r, metrics)| {
+                let label = r
+                    .manifest
+                    .variant
+                    .as_ref()
+                    .map(|v| v.to_string())
+                    .unwrap_or_else(|| r.id());
+                (label, metrics)
+            })
+            .collect();
         return Ok(cols);
     }
 
@@ -229,12 +612,14 @@ fn resolve_spec(
             .or_insert(r);
     }
 
-    let mut cols = Vec::new();
-    for (name, record) in &latest {
-        let metrics = script.collect(&record.dir)?;
-        cols.push((name.clone(), metrics));
-    }
-    Ok(cols)
+    let run_dirs: Vec<PathBuf> =
+        latest.values().map(|r| r.dir.clone()).collect();
+    let metrics = script.collect_records(&run_dirs)?;
+    Ok(latest.into_keys().zip(metrics).collect())
+}
+
+fn dirs(records: &[Record]) -> Vec<PathBuf> {
+    records.iter().map(|r| r.dir.clone()).collect()
 }
 
 pub fn analyze(
@@ -242,6 +627,7 @@ pub fn analyze(
     selectors: &[String],
     last: Option<usize>,
     analysis: Option<&str>,
+    options: &ScriptOptions,
 ) -> Result<Vec<(String, analysis::Metric)>, FossilError> {
     let unique_names: std::collections::BTreeSet<_> = selectors
         .iter()
@@ -256,15 +642,543 @@ pub fn analyze(
     }
     let mut columns = Vec::new();
     for selector in selectors {
-        columns.extend(resolve_spec(project, selector, last, analysis)?);
+        columns
+            .extend(resolve_spec(project, selector, last, analysis, options)?);
     }
 
     let mut merged: BTreeMap<String, analysis::Metric> = BTreeMap::new();
     for (label, metric) in columns {
         merged
             .entry(label)
-            .and_modify(|acc| *acc = acc.combine(&metric))
+            .and_modify(|acc| acc.absorb(&metric))
             .or_insert(metric);
     }
     Ok(merged.into_iter().collect())
 }
+
+pub fn compare(
+    project: &Project,
+    fossil_name: &str,
+    variants: &[String],
+    last: Option<usize>,
+    analysis: Option<&str>,
+    options: &ScriptOptions,
+) -> Result<Vec<(String, Samples)>, FossilError> {
+    if variants.len() < 2 {
+        return Err(FossilError::InvalidArgs(
+            "compare needs at least two variants".into(),
+        ));
+    }
+    let fossil = Fossil::load(&project.fossils_dir().join(fossil_name))?;
+
+    let script = resolve_script(&fossil, analysis, options)?;
+
+    // Every variant's records go to the scripts in one batch, then are
+    // pooled back per variant.
+    let mut spans = Vec::new();
+    let mut run_dirs = Vec::new();
+    for vname in variants {
+        let records =
+            fossil.find_records(Some(vname), Some(last.unwrap_or(1)))?;
+        if records.is_empty() {
+            return Err(FossilError::NotFound(format!(
+                "no records found for variant {vname:?}"
+            )));
+        }
+        spans.push((vname, records.len()));
+        run_dirs.extend(dirs(&records));
+    }
+    let mut metrics =
+        record_metrics(script.as_ref(), &run_dirs, options)?.into_iter();
+    Ok(spans
+        .into_iter()
+        .map(|(vname, n)| {
+            let metric = quantity::fold(metrics.by_ref().take(n));
+            (vname.clone(), metric.samples())
+        })
+        .collect())
+}
+
+/// Delete cached analysis output from every record of `fossil_name`,
+/// or of every fossil, returning how many records had any and the
+/// bytes freed.
+pub fn purge_cache(
+    project: &Project,
+    fossil_name: Option<&str>,
+) -> Result<(usize, u64), FossilError> {
+    let fossils = match fossil_name {
+        Some(name) => vec![Fossil::load(&project.fossils_dir().join(name))?],
+        None => Fossil::list_all(project.fossils_dir())?,
+    };
+    let (mut records, mut bytes) = (0, 0);
+    for fossil in &fossils {
+        for r in fossil.find_records(None, None)? {
+            let freed = analysis::ScriptCache::purge(&r.dir)?;
+            if freed > 0 {
+                records += 1;
+                bytes += freed;
+            }
+        }
+    }
+    Ok((records, bytes))
+}
+
+/// What `fossil check` holds each variant's latest record to.
+pub enum Baseline {
+    /// One record, by any `find_record` selector. Only its own
+    /// variant is checked.
+    Record(String),
+    /// The variant's latest earlier record carrying this tag.
+    Tag(String),
+    /// The variant's latest earlier record buried on this branch.
+    Branch(String),
+}
+
+impl Baseline {
+    fn matches(&self, record: &Record) -> bool {
+        match self {
+            Baseline::Record(_) => false,
+            Baseline::Tag(t) => record.manifest.tags.contains(t),
+            Baseline::Branch(b) => record.manifest.git.branch == *b,
+        }
+    }
+}
+
+/// Per-invocation knobs for `check`. Anything left unset falls back
+/// to the fossil's `[check]`.
+#[derive(Debug, Default, Clone)]
+pub struct CheckOptions {
+    /// Variants to check, every variant with records when empty.
+    pub variants: Vec<String>,
+    pub analysis: Option<String>,
+    pub test: Option<SignificanceTest>,
+    pub alpha: Option<f64>,
+    pub script: ScriptOptions,
+}
+
+/// Gate the latest record of each variant on `baseline`.
+pub fn check(
+    project: &Project,
+    fossil_name: &str,
+    baseline: &Baseline,
+    opts: &CheckOptions,
+) -> Result<Check, FossilError> {
+    let fossil = Fossil::load(&project.fossils_dir().join(fossil_name))?;
+    let mut config = fossil.config.check.clone().unwrap_or_default();
+    config.test = opts.test.unwrap_or(config.test);
+    config.alpha = opts.alpha.unwrap_or(config.alpha);
+    config.validate()?;
+
+    let options = &opts.script;
+    let variants = &opts.variants;
+    let analysis = opts.analysis.as_deref().or(config.analysis.as_deref());
+    let script = resolve_script(&fossil, analysis, options)?;
+    let mut pairs: Vec<(String, Record, Option<Record>)> = Vec::new();
+    if let Baseline::Record(selector) = baseline {
+        let base = fossil.find_record(selector)?;
+        let Some(variant) = base.manifest.variant.clone() else {
+            return Err(FossilError::InvalidArgs(format!(
+                "baseline {} has no variant to check",
+                base.id()
+            )));
+        };
+        let variant = variant.as_str().to_string();
+        if !variants.is_empty() && !variants.contains(&variant) {
+            return Err(FossilError::InvalidArgs(format!(
+                "baseline {} is of variant {variant:?}",
+                base.id()
+            )));
+        }
+        let latest = fossil
+            .find_records(Some(&variant), Some(1))?
+            .pop()
+            .filter(|r| r.id() != base.id())
+            .ok_or_else(|| {
+                FossilError::InvalidArgs(format!(
+                    "baseline {} is the latest {variant:?} record",
+                    base.id()
+                ))
+            })?;
+        pairs.push((variant, latest, Some(base)));
+    } else {
+        let names: Vec<String> = if variants.is_empty() {
+            let seen: std::collections::BTreeSet<String> = fossil
+                .find_records(None, None)?
+                .iter()
+                .filter_map(|r| r.manifest.variant.as_ref())
+                .map(|v| v.as_str().to_string())
+                .collect();
+            seen.into_iter().collect()
+        } else {
+            variants.to_vec()
+        };
+        for variant in names {
+            let mut records = fossil.find_records(Some(&variant), None)?;
+            let latest = records.pop().ok_or_else(|| {
+                FossilError::NotFound(format!(
+                    "no records found for variant {variant:?}"
+                ))
+            })?;
+            let base = records.into_iter().rev().find(|r| baseline.matches(r));
+            pairs.push((variant, latest, base));
+        }
+    }
+    // A mistyped tag or branch must not pass the gate by matching
+    // nothing.
+    if pairs.iter().all(|(_, _, base)| base.is_none()) {
+        let what = match baseline {
+            Baseline::Tag(t) => format!("tagged {t:?}"),
+            Baseline::Branch(b) => format!("on branch {b:?}"),
+            Baseline::Record(_) => unreachable!("always has a baseline"),
+        };
+        return Err(FossilError::NotFound(format!(
+            "no earlier record {what} to check against"
+        )));
+    }
+
+    let run_dirs: Vec<PathBuf> = pairs
+        .iter()
+        .flat_map(|(_, latest, base)| std::iter::once(latest).chain(base))
+        .map(|r| r.dir.clone())
+        .collect();
+    let mut samples = record_metrics(script.as_ref(), &run_dirs, options)?
+        .into_iter()
+        .map(|m| m.samples());
+    let targets = pairs
+        .into_iter()
+        .map(|(variant, latest, base)| CheckTarget {
+            variant,
+            latest: (latest.id(), samples.next().unwrap_or_default()),
+            baseline: base
+                .map(|b| (b.id(), samples.next().unwrap_or_default())),
+        })
+        .collect();
+    Ok(Check::new(
+        targets,
+        &config.thresholds,
+        config.test,
+        config.alpha,
+    ))
+}
+
+/// The records of `fossil:variant` in `order`, each with its samples
+/// of `metric`. Without a `metric` the records must have just one.
+pub fn trend(
+    project: &Project,
+    spec: &str,
+    metric: Option<&str>,
+    order: TrendOrder,
+    last: Option<usize>,
+    analysis: Option<&str>,
+    options: &ScriptOptions,
+) -> Result<(String, Vec<TrendPoint>), FossilError> {
+    let (fossil_name, variant) = spec.split_once(':').ok_or_else(|| {
+        FossilError::InvalidArgs(format!(
+            "trend needs fossil:variant, got {spec:?}"
+        ))
+    })?;
+    let fossil = Fossil::load(&project.fossils_dir().join(fossil_name))?;
+    let script = resolve_script(&fossil, analysis, options)?;
+
+    let mut records = fossil.find_records(Some(variant), last)?;
+    if records.is_empty() {
+        return Err(FossilError::NotFound(format!(
+            "no records found for variant {variant:?}"
+        )));
+    }
+    if order == TrendOrder::Commit {
+        let repo = records
+            .iter()
+            .find_map(|r| r.manifest.git.repo.clone())
+            .unwrap_or_else(|| project.path.clone());
+        records = sort_by_topology(records, &repo);
+    }
+
+    let samples: Vec<Samples> =
+        record_metrics(script.as_ref(), &dirs(&records), options)?
+            .iter()
+            .map(Metric::samples)
+            .collect();
+    let available = || {
+        let keys: std::collections::BTreeSet<&str> = samples
+            .iter()
+            .flat_map(|s| s.keys().map(String::as_str))
+            .collect();
+        keys.into_iter().collect::<Vec<_>>().join(", ")
+    };
+    let metric = match metric {
+        Some(m) => m.to_string(),
+        None => match samples.first().map(|s| s.keys().collect::<Vec<_>>()) {
+            Some(keys) if keys.len() == 1 => keys[0].clone(),
+            _ => {
+                return Err(FossilError::InvalidArgs(format!(
+                    "pick a --metric: {}",
+                    available()
+                )));
+            }
+        },
+    };
+
+    let points: Vec<TrendPoint> = records
+        .iter()
+        .zip(&samples)
+        .filter_map(|(r, s)| {
+            Some(TrendPoint {
+                record: r.id(),
+                commit: r.manifest.git.commit.clone(),
+                timestamp: r.manifest.timestamp.clone(),
+                samples: s.get(&metric)?.clone(),
+            })
+        })
+        .collect();
+    if points.is_empty() {
+        return Err(FossilError::NotFound(format!(
+            "no record has metric {metric:?}, try: {}",
+            available()
+        )));
+    }
+    Ok((metric, points))
+}
+
+/// Reorder time-sorted `records` by where their commit falls in
+/// `repo`'s history. Records at commits the repo doesn't know, e.g.
+/// from a branch since deleted, go last.
+fn sort_by_topology(records: Vec<Record>, repo: &Path) -> Vec<Record> {
+    let order = GitInfo::topo_order(repo);
+    let mut keyed: Vec<(Option<usize>, Record)> = records
+        .into_iter()
+        .map(|r| {
+            let git = &r.manifest.git;
+            (analysis::topo_position(&order, &git.sha, &git.commit), r)
+        })
+        .collect();
+    let unknown = keyed.iter().filter(|(at, _)| at.is_none()).count();
+    if unknown > 0 {
+        warning!(
+            "{unknown} records ran at commits not in {}, placing them last",
+            repo.display()
+        );
+    }
+    keyed.sort_by_key(|(at, _)| at.unwrap_or(usize::MAX));
+    keyed.into_iter().map(|(_, r)| r).collect()
+}
+
+/// The analysis script named `analysis`, or the fossil's only one.
+/// `None` when the fossil has no analysis configured and none was
+/// asked for, so callers fall back to wall time and stay useful on
+/// a bare fossil.
+pub fn resolve_script(
+    fossil: &Fossil,
+    analysis: Option<&str>,
+    options: &ScriptOptions,
+) -> Result<Option<analysis::AnalysisScript>, FossilError> {
+    if fossil.config.analyze.is_none() && analysis.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(
+        fossil
+            .resolve_analysis(analysis)?
+            .with_options(options.clone()),
+    ))
+}
+
+/// `record_metric` over several records, in the order given, with
+/// the scripts run in parallel.
+pub fn record_metrics(
+    script: Option<&analysis::AnalysisScript>,
+    run_dirs: &[PathBuf],
+    options: &ScriptOptions,
+) -> Result<Vec<Metric>, FossilError> {
+    match script {
+        Some(s) => s.collect_records(run_dirs),
+        None => run_dirs
+            .iter()
+            .map(|d| record_metric(None, d, options))
+            .collect(),
+    }
+}
+
+/// One record's metrics from `script`, or just its wall times when the
+/// fossil has no analysis to run.
+pub fn record_metric(
+    script: Option<&analysis::AnalysisScript>,
+    run_dir: &Path,
+    options: &ScriptOptions,
+) -> Result<Metric, FossilError> {
+    if let Some(s) = script {
+        return s.collect(run_dir);
+    }
+    let results = Results::load(run_dir)?;
+    let warmup = if options.include_warmup {
+        results.warmup.as_slice()
+    } else {
+        &[]
+    };
+    Ok(quantity::fold(
+        warmup.iter().chain(&results.observations).map(|o| {
+            Metric::from_json(&serde_json::json!({
+                "wall_time_ms": o.wall_time_us as f64 / 1000.0
+            }))
+        }),
+    ))
+}
+
+pub fn dig(
+    project: &Project,
+    fossil_name: &str,
+    selector: &str,
+    observation: Option<u32>,
+    json: bool,
+) -> Result<String, FossilError> {
+    let fossil = Fossil::load(&project.fossils_dir().join(fossil_name))?;
+    let record = fossil.find_record(selector)?;
+    let results = Results::load(&record.dir)?;
+
+    let chosen = match observation {
+        Some(i) => Some(
+            results
+                .observations
+                .iter()
+                .find(|o| o.iteration == i)
+                .ok_or_else(|| {
+                    FossilError::NotFound(format!(
+                        "record {} has no observation {i} (1..={})",
+                        record.id(),
+                        results.observations.len()
+                    ))
+                })?,
+        ),
+        None => None,
+    };
+
+    if json {
+        let brief = |obs: &[Observation]| -> Vec<serde_json::Value> {
+            obs.iter()
+                .map(|o| {
+                    serde_json::json!({
+                        "iteration": o.iteration,
+                        "wall_time_us": o.wall_time_us,
+                        "status": o.status,
+                        "exit_code": o.exit_code,
+                        "rusage": o.rusage,
+                    })
+                })
+                .collect()
+        };
+        let mut out = serde_json::json!({
+            "id": record.id(),
+            "path": record.dir,
+            "manifest": record.manifest,
+            "warmup": brief(&results.warmup),
+            "observations": brief(&results.observations),
+            "hooks": results.hooks.iter().map(|h| {
+                serde_json::json!({
+                    "hook": h.hook,
+                    "iteration": h.observation.iteration,
+                    "warmup": h.warmup,
+                    "wall_time_us": h.observation.wall_time_us,
+                    "status": h.observation.status,
+                    "exit_code": h.observation.exit_code,
+                })
+            }).collect::<Vec<_>>(),
+        });
+        if let Some(obs) = chosen {
+            out["observation"] = serde_json::to_value(obs).unwrap_or_default();
+        }
+        return serde_json::to_string_pretty(&out).map_err(|e| {
+            FossilError::InvalidConfig(format!("serializing record: {e}"))
+        });
+    }
+
+    let mut lines = vec![format!("{:<13}{}", "record:", record.id())];
+    lines.extend(
+        record
+            .manifest
+            .summary()
+            .into_iter()
+            .map(|(label, value)| {
+                format!("{:<13}{value}", format!("{label}:"))
+            }),
+    );
+    lines.push(String::new());
+    lines.push(format!(
+        "  {:>4}  {:>12}  {:>12}  {:>10}  {}",
+        "iter", "wall_ms", "cpu_ms", "rss_kb", "status"
+    ));
+    let rows = results
+        .warmup
+        .iter()
+        .map(|o| (format!("w{}", o.iteration), o))
+        .chain(
+            results
+                .observations
+                .iter()
+                .map(|o| (o.iteration.to_string(), o)),
+        );
+    for (iter, o) in rows {
+        let (cpu_ms, rss_kb) = match o.rusage {
+            Some(ru) => (
+                format!("{:.3}", ru.cpu_time_us() as f64 / 1000.0),
+                ru.max_rss_kb.to_string(),
+            ),
+            None => ("-".into(), "-".into()),
+        };
+        lines.push(format!(
+            "  {:>4}  {:>12.3}  {:>12}  {:>10}  {}",
+            iter,
+            o.wall_time_us as f64 / 1000.0,
+            cpu_ms,
+            rss_kb,
+            o.outcome()
+        ));
+    }
+    if !results.hooks.is_empty() {
+        lines.push(String::new());
+        lines.push(format!(
+            "  {:<12}  {:>4}  {:>12}  {}",
+            "hook", "iter", "wall_ms", "status"
+        ));
+        for h in &results.hooks {
+            let o = &h.observation;
+            let iter = match (o.iteration, h.warmup) {
+                (0, _) => "-".to_string(),
+                (i, true) => format!("w{i}"),
+                (i, false) => i.to_string(),
+            };
+            lines.push(format!(
+                "  {:<12}  {:>4}  {:>12.3}  {}",
+                h.hook.name(),
+                iter,
+                o.wall_time_us as f64 / 1000.0,
+                o.outcome()
+            ));
+        }
+    }
+    let artifacts = &record.manifest.artifacts;
+    if !artifacts.is_empty() {
+        lines.push(String::new());
+        lines.push(format!(
+            "  {:>4}  {:>10}  {:<12}  {:<3}  {}",
+            "iter", "size", "sha256", "git", "path"
+        ));
+        for a in artifacts {
+            lines.push(format!(
+                "  {:>4}  {:>10}  {:<12}  {:<3}  {}",
+                a.iteration.map_or("-".into(), |i| i.to_string()),
+                ByteSize(a.size).to_string(),
+                &a.sha256[..a.sha256.len().min(12)],
+                if a.committed { "yes" } else { "no" },
+                a.path
+            ));
+        }
+    }
+    if let Some(obs) = chosen {
+        for (name, stream) in [("stdout", &obs.stdout), ("stderr", &obs.stderr)]
+        {
+            lines.push(String::new());
+            lines.push(format!("--- {name} (iteration {}) ---", obs.iteration));
+            lines.extend(stream.iter().cloned());
+        }
+    }
+    Ok(lines.join("\n"))
+}
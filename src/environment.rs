@@ -1,60 +1,608 @@
-use serde::{Deserialize, Serialize};
-use std::path::Path;
+use crate::error::FossilError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::str::FromStr;
 
+/// [Fossil Doc] `GitInfo`
+/// -------------------------------------------------------------
+/// Git state of the code being measured: the fossil's `source`
+/// repo when it declares one, otherwise the project repo. A dirty
+/// tree is recorded as such, with its diff saved to `source.patch`
+/// next to the manifest.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct GitInfo {
     pub commit: String,
     pub branch: String,
+    /// The `source` repo this describes; absent for the project repo.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<PathBuf>,
+    #[serde(default)]
+    pub sha: String,
+    /// Tracked files differ from HEAD, staged or not.
+    #[serde(default)]
+    pub dirty: bool,
+    #[serde(default)]
+    pub untracked: usize,
 }
 
 impl GitInfo {
     pub fn current(repo: &Path) -> Self {
+        let status = Self::git(repo, &["status", "--porcelain"]);
+        let untracked = status.lines().filter(|l| l.starts_with("??")).count();
         Self {
             commit: Self::git(repo, &["rev-parse", "--short", "HEAD"]),
             branch: Self::git(repo, &["rev-parse", "--abbrev-ref", "HEAD"]),
+            repo: None,
+            sha: Self::git(repo, &["rev-parse", "HEAD"]),
+            dirty: status.lines().count() > untracked,
+            untracked,
         }
     }
 
+    /// Git state of a fossil's declared `source` repo, which unlike
+    /// the project repo has to exist already.
+    pub fn source(repo: &Path) -> Result<Self, FossilError> {
+        if Self::git(repo, &["rev-parse", "--is-inside-work-tree"]) != "true" {
+            return Err(FossilError::InvalidConfig(format!(
+                "source {} is not a git repository",
+                repo.display()
+            )));
+        }
+        Ok(Self {
+            repo: Some(repo.to_path_buf()),
+            ..Self::current(repo)
+        })
+    }
+
+    /// Uncommitted changes against HEAD, as a patch that `git apply`
+    /// reproduces on top of `sha`.
+    pub fn diff(repo: &Path) -> String {
+        Self::git_raw(repo, &["diff", "--binary", "HEAD"])
+    }
+
+    /// Full shas of every commit reachable from a ref, parents before
+    /// their children.
+    pub fn topo_order(repo: &Path) -> Vec<String> {
+        Self::git_raw(repo, &["rev-list", "--topo-order", "--reverse", "--all"])
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    /// Short form for `fossil dig`, e.g. `1a2b3c4 (main, dirty)`.
+    pub fn describe(&self) -> String {
+        let mut state = vec![self.branch.clone()];
+        if self.dirty {
+            state.push("dirty".into());
+        }
+        if self.untracked > 0 {
+            state.push(format!("{} untracked", self.untracked));
+        }
+        format!("{} ({})", self.commit, state.join(", "))
+    }
+
     fn git(repo: &Path, args: &[&str]) -> String {
+        Self::git_raw(repo, args).trim().to_string()
+    }
+
+    fn git_raw(repo: &Path, args: &[&str]) -> String {
         Command::new("git")
             .args(args)
             .current_dir(repo)
             .output()
-            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+            .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
             .unwrap_or_default()
     }
 }
 
+/// [Fossil Doc] `CpuSet`
+/// -------------------------------------------------------------
+/// A set of CPU cores, written the way `taskset -c` and sysfs do:
+/// `2`, `0-3`, `0,2,4-7`. Used to pin benchmark processes via
+/// `sched_setaffinity` before they exec.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CpuSet(Vec<usize>);
+
+impl CpuSet {
+    pub fn cores(&self) -> &[usize] {
+        &self.0
+    }
+
+    /// The affinity mask of this process, which children inherit
+    /// unless pinned. `None` where it cannot be queried.
+    #[cfg(target_os = "linux")]
+    pub fn current() -> Option<Self> {
+        // SAFETY: cpu_set_t is a plain bitmask; zeroed is empty.
+        let mut raw: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+        let size = std::mem::size_of::<libc::cpu_set_t>();
+        // SAFETY: `raw` is valid for writes of `size` bytes.
+        if unsafe { libc::sched_getaffinity(0, size, &mut raw) } != 0 {
+            return None;
+        }
+        let cores = (0..libc::CPU_SETSIZE as usize)
+            // SAFETY: `c` is below CPU_SETSIZE.
+            .filter(|&c| unsafe { libc::CPU_ISSET(c, &raw) })
+            .collect();
+        Some(Self(cores))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn current() -> Option<Self> {
+        None
+    }
+
+    /// Check the set can actually be pinned to on this machine.
+    pub fn validate(&self) -> Result<(), FossilError> {
+        if !cfg!(target_os = "linux") {
+            return Err(FossilError::InvalidConfig(
+                "CPU pinning is only supported on Linux".into(),
+            ));
+        }
+        if let Some(allowed) = Self::current()
+            && let Some(c) = self.0.iter().find(|c| !allowed.0.contains(c))
+        {
+            return Err(FossilError::InvalidConfig(format!(
+                "cpu {c} is not available to fossil (allowed: {allowed})"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Restrict `cmd` to these cores between fork and exec.
+    #[cfg(target_os = "linux")]
+    pub fn pin(&self, cmd: &mut Command) {
+        use std::os::unix::process::CommandExt;
+
+        // Build the mask up front: the pre_exec hook runs in the forked
+        // child, where allocating is off limits.
+        // SAFETY: cpu_set_t is a plain bitmask; zeroed is empty.
+        let mut raw: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+        for &c in &self.0 {
+            // SAFETY: both parsing and deserializing cap cores below
+            // CPU_SETSIZE.
+            unsafe { libc::CPU_SET(c, &mut raw) };
+        }
+        let size = std::mem::size_of::<libc::cpu_set_t>();
+        // SAFETY: sched_setaffinity is async-signal-safe and `raw` is
+        // moved into the closure.
+        unsafe {
+            cmd.pre_exec(move || {
+                if libc::sched_setaffinity(0, size, &raw) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn pin(&self, _cmd: &mut Command) {}
+}
+
+impl FromStr for CpuSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid cpu list {s:?}, expected e.g. 0-3,6");
+        let core = |c: &str| -> Result<usize, String> {
+            c.trim()
+                .parse::<usize>()
+                .ok()
+                .filter(|&c| c < libc::CPU_SETSIZE as usize)
+                .ok_or_else(invalid)
+        };
+        let mut cores = Vec::new();
+        for part in s.split(',') {
+            match part.split_once('-') {
+                Some((lo, hi)) => {
+                    let (lo, hi) = (core(lo)?, core(hi)?);
+                    if lo > hi {
+                        return Err(invalid());
+                    }
+                    cores.extend(lo..=hi);
+                }
+                None => cores.push(core(part)?),
+            }
+        }
+        cores.sort_unstable();
+        cores.dedup();
+        Ok(Self(cores))
+    }
+}
+
+impl fmt::Display for CpuSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ranges = Vec::new();
+        let mut iter = self.0.iter().copied().peekable();
+        while let Some(lo) = iter.next() {
+            let mut hi = lo;
+            while iter.peek() == Some(&(hi + 1)) {
+                hi = iter.next().unwrap();
+            }
+            ranges.push(if lo == hi {
+                lo.to_string()
+            } else {
+                format!("{lo}-{hi}")
+            });
+        }
+        f.write_str(&ranges.join(","))
+    }
+}
+
+impl Serialize for CpuSet {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for CpuSet {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        // Accept `cpus = 2` as well as `cpus = "0-3"` in fossil.toml.
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Core(usize),
+            List(String),
+        }
+        // A bare core goes through parsing too, for its bound check.
+        let list = match Repr::deserialize(deserializer)? {
+            Repr::Core(c) => c.to_string(),
+            Repr::List(s) => s,
+        };
+        list.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CpuInfo {
-    pub pinned_core: String,
+    /// Cores the benchmark could run on: the pinned set, or the
+    /// affinity fossil itself inherited when nothing was pinned.
+    #[serde(default)]
+    pub affinity: CpuSet,
+    #[serde(default)]
+    pub pinned: bool,
+    /// Only in records from before pinning was applied, when this
+    /// echoed `BENCH_CPU` without enforcing it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned_core: Option<String>,
+    /// Scaling governor of the cores in `affinity`, comma-separated
+    /// if they differ.
     pub governor: String,
-    pub boost: bool,
+    /// `None` when the machine does not expose turbo/boost state.
+    pub boost: Option<bool>,
 }
 
 impl CpuInfo {
+    pub fn current(pinned: Option<&CpuSet>) -> Self {
+        let affinity = pinned.cloned().or_else(CpuSet::current);
+        let mut governors: Vec<String> = affinity
+            .iter()
+            .flat_map(|set| set.cores())
+            .filter_map(|c| {
+                read_file(&format!(
+                    "/sys/devices/system/cpu/cpu{c}/cpufreq/scaling_governor"
+                ))
+            })
+            .collect();
+        governors.sort();
+        governors.dedup();
+        Self {
+            affinity: affinity.unwrap_or_default(),
+            pinned: pinned.is_some(),
+            pinned_core: None,
+            governor: if governors.is_empty() {
+                "unknown".into()
+            } else {
+                governors.join(",")
+            },
+            boost: Self::boost(),
+        }
+    }
+
+    /// Cores to pin to when neither the CLI nor fossil.toml ask for
+    /// any, kept for setups that already export `BENCH_CPU`.
+    pub fn bench_cpu() -> Result<Option<CpuSet>, FossilError> {
+        match std::env::var("BENCH_CPU") {
+            Ok(s) if !s.trim().is_empty() => s.parse().map(Some).map_err(|e| {
+                FossilError::InvalidConfig(format!("BENCH_CPU: {e}"))
+            }),
+            _ => Ok(None),
+        }
+    }
+
+    /// Reasons the cores in use are likely to add noise to timings.
+    pub fn noise_warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let noisy: Vec<&str> = self
+            .governor
+            .split(',')
+            .filter(|g| *g != "performance" && *g != "unknown")
+            .collect();
+        if !noisy.is_empty() {
+            warnings.push(format!(
+                "cpu {} uses the {} governor, frequency may vary between \
+                 iterations (consider `performance`)",
+                self.affinity,
+                noisy.join(",")
+            ));
+        }
+        if self.boost == Some(true) {
+            warnings.push(
+                "turbo/boost is enabled, clock speed depends on thermal \
+                 headroom"
+                    .into(),
+            );
+        }
+        warnings
+    }
+
+    /// Human readable form for `fossil dig` and the TUI.
+    pub fn describe(&self) -> String {
+        let boost = self
+            .boost
+            .map(|b| b.to_string())
+            .unwrap_or_else(|| "unknown".into());
+        let cores = match (&self.pinned_core, self.pinned) {
+            (Some(core), _) => format!("core={core} (not enforced)"),
+            (None, true) => format!("pinned={}", self.affinity),
+            (None, false) => format!("cpus={}", self.affinity),
+        };
+        format!("{cores} gov={} boost={boost}", self.governor)
+    }
+
+    fn boost() -> Option<bool> {
+        if let Some(s) = read_file("/sys/devices/system/cpu/cpufreq/boost") {
+            return Some(s != "0");
+        }
+        read_file("/sys/devices/system/cpu/intel_pstate/no_turbo")
+            .map(|s| s == "0")
+    }
+}
+
+/// [Fossil Doc] `MachineInfo`
+/// -------------------------------------------------------------
+/// Which machine a record came from, and what state it was in when
+/// the bury started. Everything is read from /proc and /sys, so a
+/// field the platform does not expose is simply left empty.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MachineInfo {
+    pub hostname: String,
+    pub cpu_model: String,
+    /// Online logical CPUs, counting SMT siblings.
+    pub logical_cpus: usize,
+    pub physical_cores: Option<usize>,
+    /// `on`, `off`, `notsupported`, ... as in /sys/devices/system/cpu/smt.
+    pub smt: String,
+    pub numa_nodes: Option<usize>,
+    pub mem_total_kb: Option<u64>,
+    /// 1, 5 and 15 minute load averages when the bury started.
+    pub load_avg: Option<[f64; 3]>,
+    pub libc: String,
+}
+
+impl MachineInfo {
     pub fn current() -> Self {
-        let core = Self::bench_cpu();
+        let cpuinfo = read_file("/proc/cpuinfo").unwrap_or_default();
+        let field = |line: &str, key: &str| -> Option<String> {
+            let (k, v) = line.split_once(':')?;
+            (k.trim() == key).then(|| v.trim().to_string())
+        };
+        let cpu_model = cpuinfo
+            .lines()
+            .find_map(|l| field(l, "model name").or_else(|| field(l, "Model")))
+            .unwrap_or_else(|| "unknown".into());
+        // A core is a distinct (package, core) pair; SMT siblings share one.
+        let mut cores: Vec<(String, String)> = cpuinfo
+            .split("\n\n")
+            .filter_map(|block| {
+                let get = |key| block.lines().find_map(|l| field(l, key));
+                Some((get("physical id")?, get("core id")?))
+            })
+            .collect();
+        cores.sort();
+        cores.dedup();
+
+        let count = |path: &str| -> Option<usize> {
+            read_file(path)?
+                .parse::<CpuSet>()
+                .ok()
+                .map(|s| s.cores().len())
+        };
+
         Self {
-            governor: Self::read_sysfs(&format!(
-                "/sys/devices/system/cpu/cpu{core}/cpufreq/scaling_governor"
-            ))
-            .unwrap_or_else(|| "unknown".into()),
-            boost: Self::read_sysfs("/sys/devices/system/cpu/cpufreq/boost")
-                .map(|s| s != "0")
-                .unwrap_or(true),
-            pinned_core: core,
+            hostname: read_file("/proc/sys/kernel/hostname")
+                .unwrap_or_else(|| "unknown".into()),
+            cpu_model,
+            logical_cpus: count("/sys/devices/system/cpu/online")
+                .or_else(|| {
+                    std::thread::available_parallelism()
+                        .ok()
+                        .map(Into::into)
+                })
+                .unwrap_or(0),
+            physical_cores: (!cores.is_empty()).then_some(cores.len()),
+            smt: read_file("/sys/devices/system/cpu/smt/control")
+                .unwrap_or_else(|| "unknown".into()),
+            numa_nodes: count("/sys/devices/system/node/online"),
+            mem_total_kb: read_file("/proc/meminfo").and_then(|m| {
+                m.lines()
+                    .find_map(|l| field(l, "MemTotal"))?
+                    .trim_end_matches("kB")
+                    .trim()
+                    .parse()
+                    .ok()
+            }),
+            load_avg: read_file("/proc/loadavg").and_then(|s| {
+                let mut it = s.split_whitespace().map(|x| x.parse().ok());
+                Some([it.next()??, it.next()??, it.next()??])
+            }),
+            libc: Self::libc_version(),
         }
     }
 
-    fn bench_cpu() -> String {
-        std::env::var("BENCH_CPU").unwrap_or_else(|_| "2".into())
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    fn libc_version() -> String {
+        // SAFETY: returns a pointer to a static, NUL-terminated string.
+        let v =
+            unsafe { std::ffi::CStr::from_ptr(libc::gnu_get_libc_version()) };
+        format!("glibc {}", v.to_string_lossy())
     }
 
-    fn read_sysfs(path: &str) -> Option<String> {
-        std::fs::read_to_string(path)
-            .ok()
-            .map(|s| s.trim().to_string())
+    #[cfg(all(target_os = "linux", target_env = "musl"))]
+    fn libc_version() -> String {
+        "musl".into()
     }
+
+    #[cfg(not(all(
+        target_os = "linux",
+        any(target_env = "gnu", target_env = "musl")
+    )))]
+    fn libc_version() -> String {
+        "unknown".into()
+    }
+
+    /// One-line hardware description for `fossil dig` and the TUI.
+    pub fn describe(&self) -> String {
+        let mut parts = vec![self.cpu_model.clone()];
+        parts.push(match self.physical_cores {
+            Some(c) => format!("{} cpus / {c} cores", self.logical_cpus),
+            None => format!("{} cpus", self.logical_cpus),
+        });
+        parts.push(format!("smt={}", self.smt));
+        if let Some(n) = self.numa_nodes {
+            parts.push(format!("numa={n}"));
+        }
+        if let Some(kb) = self.mem_total_kb {
+            parts.push(format!("{:.1} GiB", kb as f64 / (1024.0 * 1024.0)));
+        }
+        parts.join(", ")
+    }
+}
+
+/// [Fossil Doc] `RunEnv`
+/// -------------------------------------------------------------
+/// The exact environment every iteration and hook of a run gets:
+/// the caller's, or with `clear_env` only the variables `keep_env`
+/// lets through, plus the fossil's and variant's `env` tables.
+/// Recorded in the manifest, with values of keys matching
+/// `redact_env` replaced.
+///
+/// ```toml
+/// clear_env = true
+/// keep_env = ["PATH", "HOME", "LC_*"]
+///
+/// [env]
+/// OMP_NUM_THREADS = "4"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RunEnv {
+    pub cleared: bool,
+    pub vars: BTreeMap<String, String>,
+}
+
+impl RunEnv {
+    /// Kept from the caller under `clear_env` when `keep_env` is unset.
+    pub const DEFAULT_KEEP: &[&str] =
+        &["PATH", "HOME", "USER", "LANG", "LC_*", "TERM", "TMPDIR"];
+    /// Redacted when `redact_env` is unset.
+    pub const DEFAULT_REDACT: &[&str] = &[
+        "*TOKEN*",
+        "*SECRET*",
+        "*PASSWORD*",
+        "*PASSWD*",
+        "*CREDENTIAL*",
+        "*API_KEY*",
+        "*PRIVATE_KEY*",
+        "*ACCESS_KEY*",
+    ];
+    const REDACTED: &str = "<redacted>";
+
+    pub fn resolve(
+        set: BTreeMap<String, String>,
+        clear: bool,
+        keep: &[String],
+    ) -> Result<Self, FossilError> {
+        let keep = patterns("keep_env", keep)?;
+        let mut vars: BTreeMap<String, String> = std::env::vars()
+            .filter(|(k, _)| !clear || keep.iter().any(|p| p.matches(k)))
+            .collect();
+        vars.extend(set);
+        Ok(Self {
+            cleared: clear,
+            vars,
+        })
+    }
+
+    /// Give `cmd` exactly these variables and nothing else.
+    pub fn apply(&self, cmd: &mut Command) {
+        cmd.env_clear();
+        cmd.envs(&self.vars);
+    }
+
+    /// A copy safe to write to disk, with the values of keys that
+    /// match any of `secrets` (case-insensitively) replaced.
+    pub fn redacted(&self, secrets: &[String]) -> Result<Self, FossilError> {
+        let secrets = patterns("redact_env", secrets)?;
+        let opts = glob::MatchOptions {
+            case_sensitive: false,
+            ..Default::default()
+        };
+        let vars = self
+            .vars
+            .iter()
+            .map(|(k, v)| {
+                let secret = secrets.iter().any(|p| p.matches_with(k, opts));
+                let v = if secret { Self::REDACTED } else { v.as_str() };
+                (k.clone(), v.to_string())
+            })
+            .collect();
+        Ok(Self {
+            cleared: self.cleared,
+            vars,
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        let redacted = self
+            .vars
+            .values()
+            .filter(|v| *v == Self::REDACTED)
+            .count();
+        let mut s = format!(
+            "{} vars, {}",
+            self.vars.len(),
+            if self.cleared { "cleared" } else { "inherited" }
+        );
+        if redacted > 0 {
+            s.push_str(&format!(", {redacted} redacted"));
+        }
+        s
+    }
+}
+
+fn patterns(
+    key: &str,
+    raw: &[String],
+) -> Result<Vec<glob::Pattern>, FossilError> {
+    raw.iter()
+        .map(|p| {
+            glob::Pattern::new(p).map_err(|e| {
+                FossilError::InvalidConfig(format!("{key} pattern {p:?}: {e}"))
+            })
+        })
+        .collect()
+}
+
+fn read_file(path: &str) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_string())
 }
@@ -14,13 +14,33 @@ pub enum FossilError {
     #[error("{0}")]
     InvalidArgs(String),
 
-    #[error("{command:?} failed on iteration {iteration} (exit {exit_code})")]
+    #[error("{command:?} failed on iteration {iteration} ({outcome})")]
     CommandFailed {
         command: String,
         iteration: u32,
-        exit_code: i32,
+        outcome: String,
     },
 
+    #[error("{command:?} timed out on iteration {iteration} after {seconds}s")]
+    TimedOut {
+        command: String,
+        iteration: u32,
+        seconds: f64,
+    },
+
+    #[error("{hook} hook {command:?} failed ({outcome})")]
+    HookFailed {
+        hook: &'static str,
+        command: String,
+        outcome: String,
+    },
+
+    #[error("check failed: {0} metrics regressed or missing")]
+    CheckFailed(usize),
+
+    #[error("cancelled")]
+    Cancelled,
+
     #[error("git {args}: {stderr}")]
     Git { args: String, stderr: String },
 
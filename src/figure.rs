@@ -65,7 +65,8 @@ impl<'a> Figure<'a> {
         fossil: &Fossil,
         columns: &[(String, analysis::Metric)],
     ) -> Result<(), FossilError> {
-        let json = analysis::columns_to_json(columns)?;
+        let json =
+            analysis::columns_to_json(columns, fossil.config.summary_fields())?;
 
         let script_path = self.entry.script.resolve(&fossil.path);
         let out_path = self.output_path(fossil);
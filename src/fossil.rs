@@ -1,8 +1,13 @@
-use crate::analysis::{AnalysisName, AnalysisScript};
+use crate::analysis::stats::SignificanceTest;
+use crate::analysis::{AnalysisName, AnalysisScript, SummaryField, Threshold};
+use crate::artifact::ArtifactConfig;
+use crate::command::{CommandSpec, expand_env};
 use crate::entity::DirEntity;
+use crate::environment::{CpuSet, RunEnv};
 use crate::error::FossilError;
 use crate::manifest::Manifest;
 use crate::record::Record;
+use crate::schedule::Order;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
@@ -49,10 +54,376 @@ impl std::fmt::Display for FossilVariantKey {
 
 /// [Fossil Doc] `ResolvedVariant`
 /// To give a fossil variant a type. Produced when a
-/// variant we ask for matches one declared in the fossil.toml
+/// variant we ask for matches one declared in the fossil.toml,
+/// or for an ad-hoc `-- <cmd>` which has no name.
 pub struct ResolvedVariant {
-    pub name: FossilVariantKey,
-    pub command: String,
+    pub name: Option<FossilVariantKey>,
+    pub command: CommandSpec,
+    pub timeout: Option<f64>,
+    /// Matrix parameters the variant was generated from, if any.
+    pub params: MatrixParams,
+    pub hooks: Hooks,
+    /// The variant's `env` table, expanded and layered over the
+    /// fossil's.
+    pub env: BTreeMap<String, String>,
+}
+
+impl ResolvedVariant {
+    pub fn untagged(command: CommandSpec) -> Self {
+        Self {
+            name: None,
+            command,
+            timeout: None,
+            params: MatrixParams::new(),
+            hooks: Hooks::default(),
+            env: BTreeMap::new(),
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        self.name
+            .as_ref()
+            .map(FossilVariantKey::as_str)
+            .unwrap_or("untagged")
+    }
+}
+
+/// [Fossil Doc] `Hooks`
+/// -------------------------------------------------------------
+/// Commands run around the measured one, outside the timed region:
+/// `setup` once before the first iteration (warmups included),
+/// `teardown` once after the last, `before_each` and `after_each`
+/// around every iteration. A variant's hook replaces the fossil's.
+/// Their output is logged under `hooks` in results.json.
+///
+/// ```toml
+/// setup = "make -C src bench"
+/// before_each = "sync; echo 3 | sudo tee /proc/sys/vm/drop_caches"
+/// after_each = ["rm", "-rf", "/tmp/bench-scratch"]
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Hooks {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub setup: Option<CommandSpec>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub teardown: Option<CommandSpec>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before_each: Option<CommandSpec>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after_each: Option<CommandSpec>,
+}
+
+impl Hooks {
+    /// These hooks, falling back to `base` for any left unset.
+    pub fn or(&self, base: &Hooks) -> Hooks {
+        Hooks {
+            setup: self.setup.clone().or_else(|| base.setup.clone()),
+            teardown: self.teardown.clone().or_else(|| base.teardown.clone()),
+            before_each: self
+                .before_each
+                .clone()
+                .or_else(|| base.before_each.clone()),
+            after_each: self
+                .after_each
+                .clone()
+                .or_else(|| base.after_each.clone()),
+        }
+    }
+
+    pub fn map(&self, f: impl Fn(&CommandSpec) -> CommandSpec) -> Hooks {
+        Hooks {
+            setup: self.setup.as_ref().map(&f),
+            teardown: self.teardown.as_ref().map(&f),
+            before_each: self.before_each.as_ref().map(&f),
+            after_each: self.after_each.as_ref().map(&f),
+        }
+    }
+}
+
+/// A variant entry in fossil.toml. Either just the command, as a
+/// shell string or argv array, or a table with per-variant settings:
+///
+/// ```toml
+/// [variants]
+/// O2 = ["gcc", "-O2", "workload.c"]
+/// O3 = { command = ["gcc", "-O3", "workload.c"], timeout = 60 }
+/// omp = { command = "./bench", env = { OMP_NUM_THREADS = "8" } }
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct VariantConfig {
+    pub command: CommandSpec,
+    /// Seconds before an iteration is killed, overriding the fossil's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<f64>,
+    /// Variables set on top of the fossil's `env`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    #[serde(flatten)]
+    pub hooks: Hooks,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum VariantRepr {
+    Command(CommandSpec),
+    Table(VariantConfig),
+}
+
+fn deserialize_variants<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<FossilVariantKey, VariantConfig>, D::Error> {
+    let raw =
+        BTreeMap::<FossilVariantKey, VariantRepr>::deserialize(deserializer)?;
+    Ok(raw
+        .into_iter()
+        .map(|(k, v)| {
+            let v = match v {
+                VariantRepr::Command(command) => VariantConfig {
+                    command,
+                    timeout: None,
+                    env: BTreeMap::new(),
+                    hooks: Hooks::default(),
+                },
+                VariantRepr::Table(t) => t,
+            };
+            (k, v)
+        })
+        .collect())
+}
+
+/// One value of a matrix axis. Kept as the TOML type it was written
+/// in so manifests record `threads = 4` as a number, and substituted
+/// into commands as text.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum MatrixValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+impl std::fmt::Display for MatrixValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Int(i) => write!(f, "{i}"),
+            Self::Float(x) => write!(f, "{x}"),
+            Self::Str(s) => f.write_str(s),
+        }
+    }
+}
+
+pub type MatrixParams = BTreeMap<String, MatrixValue>;
+
+/// [Fossil Doc] `MatrixConfig`
+/// -------------------------------------------------------------
+/// A parameter sweep. Every combination of the axes' values becomes
+/// a variant named after its parameters, e.g. `opt=O2,threads=4`,
+/// running the command template with `$opt` and `$threads` filled
+/// in. `exclude` drops the combinations a rule matches (a rule may
+/// name only some axes); `include` adds combinations as written.
+///
+/// ```toml
+/// [matrix]
+/// command = ["gcc", "-$opt", "-DTHREADS=$threads", "workload.c"]
+/// opt = ["O0", "O2", "O3"]
+/// threads = [1, 4]
+/// exclude = [{ opt = "O0", threads = 4 }]
+/// include = [{ opt = "Ofast", threads = 8 }]
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MatrixConfig {
+    pub command: CommandSpec,
+    /// Seconds before an iteration is killed, overriding the fossil's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<f64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<MatrixParams>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<MatrixParams>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Hooks may use the axes' `$placeholders` too. Declared before
+    /// `axes` so their keys are not mistaken for axes.
+    #[serde(flatten)]
+    pub hooks: Hooks,
+    #[serde(flatten)]
+    pub axes: BTreeMap<String, Vec<MatrixValue>>,
+}
+
+impl MatrixConfig {
+    /// The parameters of every variant the matrix generates, in axis
+    /// order, followed by any `include`s.
+    pub fn combinations(&self) -> Vec<MatrixParams> {
+        let mut combos = if self.axes.is_empty() {
+            Vec::new()
+        } else {
+            vec![MatrixParams::new()]
+        };
+        for (axis, values) in &self.axes {
+            combos = combos
+                .into_iter()
+                .flat_map(|combo| {
+                    values.iter().map(move |v| {
+                        let mut combo = combo.clone();
+                        combo.insert(axis.clone(), v.clone());
+                        combo
+                    })
+                })
+                .collect();
+        }
+        combos.retain(|combo| {
+            !self
+                .exclude
+                .iter()
+                .any(|rule| rule.iter().all(|(k, v)| combo.get(k) == Some(v)))
+        });
+        for extra in &self.include {
+            if !combos.contains(extra) {
+                combos.push(extra.clone());
+            }
+        }
+        combos
+    }
+
+    pub fn variant_key(params: &MatrixParams) -> FossilVariantKey {
+        let parts: Vec<String> = params
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect();
+        FossilVariantKey::new(parts.join(","))
+    }
+
+    /// Fill `$axis` placeholders in `template`. Longer names go first
+    /// so `$opt` does not clobber the start of `$opt_level`.
+    fn substitute(params: &MatrixParams, template: &str) -> String {
+        let mut names: Vec<&String> = params.keys().collect();
+        names.sort_by_key(|k| std::cmp::Reverse(k.len()));
+        names.into_iter().fold(template.to_string(), |acc, k| {
+            acc.replace(&format!("${k}"), &params[k].to_string())
+        })
+    }
+}
+
+/// [Fossil Doc] `AdaptiveConfig`
+/// -------------------------------------------------------------
+/// Iterate until the mean of `metric` is known well enough instead
+/// of a fixed number of times: stop once the confidence interval's
+/// half-width is within `target` of the mean, or at the cap.
+///
+/// ```toml
+/// [adaptive]
+/// min_iterations = 5
+/// max_iterations = 200
+/// target = 0.02           # ±2% of the mean
+/// confidence = 95
+/// metric = "wall_time_ms" # or a metric from an analysis script
+/// analysis = "phases"     # the script producing `metric`
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AdaptiveConfig {
+    pub min_iterations: u32,
+    pub max_iterations: u32,
+    pub target: f64,
+    pub confidence: f64,
+    pub metric: String,
+    pub analysis: Option<AnalysisName>,
+}
+
+impl Default for AdaptiveConfig {
+    fn default() -> Self {
+        Self {
+            min_iterations: 5,
+            max_iterations: 100,
+            target: 0.02,
+            confidence: 95.0,
+            metric: "wall_time_ms".into(),
+            analysis: None,
+        }
+    }
+}
+
+impl AdaptiveConfig {
+    pub fn validate(&self) -> Result<(), FossilError> {
+        let problem = if self.min_iterations < 2 {
+            "min_iterations must be at least 2"
+        } else if self.max_iterations < self.min_iterations {
+            "max_iterations must be at least min_iterations"
+        } else if self.target <= 0.0 || self.target.is_nan() {
+            "target must be positive"
+        } else if !(self.confidence > 0.0 && self.confidence < 100.0) {
+            "confidence must be between 0 and 100"
+        } else {
+            return Ok(());
+        };
+        Err(FossilError::InvalidConfig(format!("adaptive: {problem}")))
+    }
+
+    /// Whether `metric` comes from an analysis script rather than
+    /// the recorded wall time.
+    pub fn uses_analysis(&self) -> bool {
+        self.analysis.is_some() || self.metric != "wall_time_ms"
+    }
+}
+
+/// [Fossil Doc] `CheckConfig`
+/// -------------------------------------------------------------
+/// What `fossil check` gates on. Each metric gets a `Threshold`
+/// whose sign says which way is worse; a metric fails when it moves
+/// that way by more than the threshold and significantly at `alpha`.
+/// A thresholded metric missing from either record fails too. There
+/// is no default gate, so at least one threshold is required.
+///
+/// ```toml
+/// [check]
+/// analysis = "phases"       # the script producing the metrics
+/// test = "mann-whitney"
+/// alpha = 0.01
+///
+/// [check.thresholds]
+/// wall_time_ms = "+5%"      # more than 5% slower fails
+/// "phases.parse_ms" = "+2"  # more than 2ms slower fails
+/// throughput = "-3%"        # more than 3% lower fails
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CheckConfig {
+    pub analysis: Option<AnalysisName>,
+    pub test: SignificanceTest,
+    pub alpha: f64,
+    pub thresholds: BTreeMap<String, Threshold>,
+}
+
+impl Default for CheckConfig {
+    fn default() -> Self {
+        Self {
+            analysis: None,
+            test: SignificanceTest::Welch,
+            alpha: 0.05,
+            thresholds: BTreeMap::new(),
+        }
+    }
+}
+
+impl CheckConfig {
+    pub fn validate(&self) -> Result<(), FossilError> {
+        if !(self.alpha > 0.0 && self.alpha < 1.0) {
+            return Err(FossilError::InvalidConfig(
+                "check: alpha must be between 0 and 1".into(),
+            ));
+        }
+        if self.thresholds.is_empty() {
+            return Err(FossilError::InvalidConfig(
+                "check: no thresholds, add a [check.thresholds] table such \
+                 as wall_time_ms = \"+5%\""
+                    .into(),
+            ));
+        }
+        Ok(())
+    }
 }
 
 pub type AnalysisMap = BTreeMap<AnalysisName, String>;
@@ -69,13 +440,46 @@ pub struct FossilConfig {
     pub name: FossilName,
     pub description: Option<String>,
     pub default_iterations: u32,
+    pub warmup: u32,
+    pub adaptive: Option<AdaptiveConfig>,
+    pub check: Option<CheckConfig>,
     pub analyze: Option<AnalysisMap>,
+    pub summary: Option<Vec<SummaryField>>,
     #[serde(alias = "visualize")]
     pub figures: Option<BTreeMap<String, FigureEntry>>,
     pub allow_failure: bool,
+    /// Seconds before an iteration is killed and recorded as timed out.
+    pub timeout: Option<f64>,
+    /// Cores to pin every iteration to, e.g. `"2"` or `"0-3"`.
+    pub cpus: Option<CpuSet>,
+    /// How iterations of different variants are interleaved when
+    /// burying all of them.
+    pub order: Order,
+    /// Seed for `order = "shuffle"`; picked per run when unset.
+    pub seed: Option<u64>,
     pub workdir: Option<FossilPath>,
+    #[serde(flatten)]
+    pub hooks: Hooks,
+    /// Git repo of the code under test, whose commit and uncommitted
+    /// changes are recorded with every bury.
+    pub source: Option<FossilPath>,
+    /// Files to keep from each run, see `ArtifactConfig`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<ArtifactConfig>,
     pub variables: BTreeMap<String, String>,
-    pub variants: BTreeMap<FossilVariantKey, String>,
+    /// Variables set for every iteration and hook, see `RunEnv`.
+    /// Values may use `$variables`, constants and the caller's env.
+    pub env: BTreeMap<String, String>,
+    /// Start from an empty environment rather than the caller's.
+    pub clear_env: bool,
+    /// Caller variables kept under `clear_env`, as globs.
+    pub keep_env: Option<Vec<String>>,
+    /// Variables whose values are redacted in the manifest, as
+    /// case-insensitive globs.
+    pub redact_env: Option<Vec<String>>,
+    #[serde(deserialize_with = "deserialize_variants")]
+    pub variants: BTreeMap<FossilVariantKey, VariantConfig>,
+    pub matrix: Option<MatrixConfig>,
 }
 
 impl Default for FossilConfig {
@@ -84,12 +488,28 @@ impl Default for FossilConfig {
             name: String::new(),
             description: None,
             default_iterations: 10,
+            warmup: 0,
+            adaptive: None,
+            check: None,
             analyze: None,
+            summary: None,
             figures: None,
             allow_failure: false,
+            timeout: None,
+            cpus: None,
+            order: Order::default(),
+            seed: None,
             workdir: None,
+            hooks: Hooks::default(),
+            source: None,
+            artifacts: Vec::new(),
             variables: BTreeMap::new(),
+            env: BTreeMap::new(),
+            clear_env: false,
+            keep_env: None,
+            redact_env: None,
             variants: BTreeMap::new(),
+            matrix: None,
         }
     }
 }
@@ -99,6 +519,49 @@ impl FossilConfig {
         self.description.as_deref().unwrap_or("")
     }
 
+    /// Summary statistics reported for each Scalar, falling back to
+    /// mean and stddev when fossil.toml does not choose any.
+    pub fn summary_fields(&self) -> &[SummaryField] {
+        self.summary
+            .as_deref()
+            .unwrap_or(SummaryField::DEFAULT)
+    }
+
+    /// Every variant name: those declared under `[variants]`, then
+    /// those generated by `[matrix]`.
+    pub fn variant_keys(&self) -> Vec<FossilVariantKey> {
+        let mut keys: Vec<FossilVariantKey> =
+            self.variants.keys().cloned().collect();
+        if let Some(matrix) = &self.matrix {
+            keys.extend(
+                matrix
+                    .combinations()
+                    .iter()
+                    .map(MatrixConfig::variant_key)
+                    .filter(|k| !self.variants.contains_key(k)),
+            );
+        }
+        keys
+    }
+
+    pub fn keep_env(&self) -> Vec<String> {
+        self.keep_env.clone().unwrap_or_else(|| {
+            RunEnv::DEFAULT_KEEP
+                .iter()
+                .map(|s| s.to_string())
+                .collect()
+        })
+    }
+
+    pub fn redact_env(&self) -> Vec<String> {
+        self.redact_env.clone().unwrap_or_else(|| {
+            RunEnv::DEFAULT_REDACT
+                .iter()
+                .map(|s| s.to_string())
+                .collect()
+        })
+    }
+
     pub fn all_scripts(&self) -> Vec<&str> {
         let mut scripts = Vec::new();
         if let Some(ref map) = self.analyze {
@@ -227,7 +690,14 @@ impl Fossil {
             })
             .collect();
 
-        records.sort_by(|a, b| a.manifest.timestamp.cmp(&b.manifest.timestamp));
+        // Ids start with the timestamp to the millisecond, breaking ties
+        // between records buried within the same second.
+        records.sort_by(|a, b| {
+            a.manifest
+                .timestamp
+                .cmp(&b.manifest.timestamp)
+                .then_with(|| a.dir.cmp(&b.dir))
+        });
         if let Some(n) = last {
             let skip = records.len().saturating_sub(n);
             records.drain(..skip);
@@ -235,6 +705,49 @@ impl Fossil {
         Ok(records)
     }
 
+    /// Resolve a single record from a selector: a record id (or a
+    /// unique prefix of one), `variant:latest`, or a prefix of the
+    /// manifest timestamp such as `2024-05-01T14`.
+    pub fn find_record(&self, selector: &str) -> Result<Record, FossilError> {
+        if let Some((vname, which)) = selector.split_once(':')
+            && which == "latest"
+        {
+            return self
+                .find_records(Some(vname), Some(1))?
+                .pop()
+                .ok_or_else(|| {
+                    FossilError::NotFound(format!(
+                        "no records found for variant {vname:?}"
+                    ))
+                });
+        }
+
+        let mut matches: Vec<Record> = self
+            .find_records(None, None)?
+            .into_iter()
+            .filter(|r| {
+                r.id().starts_with(selector)
+                    || r.manifest.timestamp.starts_with(selector)
+            })
+            .collect();
+        if let Some(i) = matches.iter().position(|r| r.id() == selector) {
+            return Ok(matches.swap_remove(i));
+        }
+        match matches.len() {
+            0 => Err(FossilError::NotFound(format!(
+                "no record matches {selector:?}"
+            ))),
+            1 => Ok(matches.pop().unwrap()),
+            n => {
+                let ids: Vec<String> = matches.iter().map(Record::id).collect();
+                Err(FossilError::InvalidArgs(format!(
+                    "{selector:?} matches {n} records: {}",
+                    ids.join(", ")
+                )))
+            }
+        }
+    }
+
     pub fn expand(
         &self,
         template: &str,
@@ -250,28 +763,86 @@ impl Fossil {
         result
     }
 
+    /// Expand variables in a command. Argv commands never see a shell,
+    /// so environment variables are expanded here, per argument.
+    pub fn expand_command(
+        &self,
+        command: &CommandSpec,
+        project_constants: &BTreeMap<String, String>,
+    ) -> CommandSpec {
+        match command {
+            CommandSpec::Shell(_) => {
+                command.map(|s| self.expand(s, project_constants))
+            }
+            CommandSpec::Argv(_) => {
+                command.map(|a| expand_env(&self.expand(a, project_constants)))
+            }
+        }
+    }
+
+    /// Expand an `env` table's values like an argv element.
+    pub fn expand_env(
+        &self,
+        env: &BTreeMap<String, String>,
+        project_constants: &BTreeMap<String, String>,
+    ) -> BTreeMap<String, String> {
+        env.iter()
+            .map(|(k, v)| {
+                (k.clone(), expand_env(&self.expand(v, project_constants)))
+            })
+            .collect()
+    }
+
     pub fn resolve_variant(
         &self,
         name: &FossilVariantKey,
         project_constants: &BTreeMap<String, String>,
     ) -> Result<ResolvedVariant, FossilError> {
-        let (key, command) = self
-            .config
-            .variants
-            .get_key_value(name)
-            .ok_or_else(|| {
-                // Find variants registered in the fossil.toml
-                let available: Vec<&str> = self
-                    .config
-                    .variants
-                    .keys()
-                    .map(|k| k.as_str())
-                    .collect();
-                FossilError::unknown("variant", name.as_str(), &available)
-            })?;
-        Ok(ResolvedVariant {
-            name: key.clone(),
-            command: self.expand(command, project_constants),
-        })
+        if let Some((key, variant)) = self.config.variants.get_key_value(name) {
+            return Ok(ResolvedVariant {
+                name: Some(key.clone()),
+                command: self
+                    .expand_command(&variant.command, project_constants),
+                timeout: variant.timeout,
+                params: MatrixParams::new(),
+                env: self.expand_env(&variant.env, project_constants),
+                hooks: variant
+                    .hooks
+                    .or(&self.config.hooks)
+                    .map(|c| self.expand_command(c, project_constants)),
+            });
+        }
+        if let Some(matrix) = &self.config.matrix
+            && let Some(params) = matrix
+                .combinations()
+                .into_iter()
+                .find(|p| MatrixConfig::variant_key(p) == *name)
+        {
+            let expand = |c: &CommandSpec| {
+                let c = c.map(|s| MatrixConfig::substitute(&params, s));
+                self.expand_command(&c, project_constants)
+            };
+            return Ok(ResolvedVariant {
+                name: Some(name.clone()),
+                command: expand(&matrix.command),
+                timeout: matrix.timeout,
+                hooks: matrix.hooks.or(&self.config.hooks).map(expand),
+                env: self.expand_env(
+                    &matrix
+                        .env
+                        .iter()
+                        .map(|(k, v)| {
+                            (k.clone(), MatrixConfig::substitute(&params, v))
+                        })
+                        .collect(),
+                    project_constants,
+                ),
+                params,
+            });
+        }
+        // Find variants registered in the fossil.toml
+        let keys = self.config.variant_keys();
+        let available: Vec<&str> = keys.iter().map(|k| k.as_str()).collect();
+        Err(FossilError::unknown("variant", name.as_str(), &available))
     }
 }
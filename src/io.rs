@@ -12,6 +12,13 @@ macro_rules! error {
 }
 pub(crate) use error;
 
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        eprintln!("warning: {}", format_args!($($arg)*))
+    };
+}
+pub(crate) use warning;
+
 macro_rules! output {
     ($($arg:tt)*) => {
         println!($($arg)*)
@@ -1,5 +1,7 @@
 mod analysis;
+mod artifact;
 mod cli;
+mod command;
 mod commands;
 mod entity;
 mod environment;
@@ -12,12 +14,15 @@ mod manifest;
 mod project;
 mod record;
 mod runner;
+mod schedule;
 mod tui;
 
+use artifact::ByteSize;
 use clap::Parser;
-use cli::{Cli, Cmd, ProjectCmd};
+use cli::{CacheCmd, Cli, Cmd, ProjectCmd};
+use command::CommandSpec;
 use entity::DirEntity;
-use fossil::{Fossil, FossilVariantKey};
+use fossil::{Fossil, FossilVariantKey, ResolvedVariant};
 use io::{error, output, status};
 use project::Project;
 
@@ -77,7 +82,16 @@ fn run() -> Result<(), error::FossilError> {
         Cmd::Bury {
             fossil: fname,
             iterations,
+            warmup,
+            target_ci,
+            min_iterations,
+            max_iterations,
+            timeout,
+            cpus,
+            order,
+            seed,
             variant,
+            tags,
             dry_run,
             silent,
             command,
@@ -105,61 +119,65 @@ fn run() -> Result<(), error::FossilError> {
                         ));
                     }
                     (None, false) => {
-                        output!("{}", command.join(" "));
+                        output!("{}", CommandSpec::from_args(command));
                         return Ok(());
                     }
                     (None, true) => f
                         .config
-                        .variants
-                        .keys()
+                        .variant_keys()
+                        .iter()
                         .map(|k| {
                             f.resolve_variant(k, &project.config.constants)
                         })
                         .collect::<Result<_, _>>()?,
                 };
                 for v in &variants {
-                    output!("[{}]\n{}\n", v.name, v.command);
+                    output!("[{}]\n{}\n", v.label(), v.command);
                 }
                 return Ok(());
             }
 
+            let opts = commands::BuryOptions {
+                iterations,
+                warmup,
+                target_ci,
+                min_iterations,
+                max_iterations,
+                timeout,
+                cpus,
+                order,
+                seed,
+                tags,
+                silent,
+                progress: None,
+                cancel: None,
+            };
             match (variant, command.is_empty()) {
                 (Some(ref name), true) => {
                     let v =
                         f.resolve_variant(name, &project.config.constants)?;
-                    commands::bury(
-                        &f,
-                        &project,
-                        iterations,
-                        Some(v.name),
-                        v.command,
-                        silent,
-                    )?;
+                    commands::bury(&f, &project, v, &opts)?;
                     Ok(())
                 }
                 (Some(_), false) => Err(error::FossilError::InvalidArgs(
                     "cannot specify both --variant and -- <command>".into(),
                 )),
                 (None, false) => {
-                    commands::bury(
-                        &f,
-                        &project,
-                        iterations,
-                        None,
-                        command.join(" "),
-                        silent,
-                    )?;
+                    let v = ResolvedVariant::untagged(CommandSpec::from_args(
+                        command,
+                    ));
+                    commands::bury(&f, &project, v, &opts)?;
                     Ok(())
                 }
-                (None, true) => {
-                    Ok(commands::bury_all(&f, &project, iterations, silent)?)
-                }
+                (None, true) => Ok(commands::bury_all(&f, &project, &opts)?),
             }
         }
         Cmd::Analyze {
             selectors,
             last,
             analysis,
+            summary,
+            script,
         } => {
             if selectors.is_empty() {
                 let project = Project::resolve(
@@ -180,8 +198,140 @@ fn run() -> Result<(), error::FossilError> {
                 &selectors,
                 last,
                 analysis.as_deref(),
+                &script.into(),
+            )?;
+            let fields = if summary.is_empty() {
+                Fossil::load(&project.fossils_dir().join(fossil_hint))?
+                    .config
+                    .summary_fields()
+                    .to_vec()
+            } else {
+                summary
+            };
+            output!("{}", analysis::columns_to_json(&columns, &fields)?);
+            Ok(())
+        }
+        Cmd::Dig {
+            fossil: fname,
+            record,
+            observation,
+            json,
+        } => {
+            let project = Project::resolve(
+                &projects_dir,
+                cli.project.as_deref(),
+                Some(&fname),
+            )?;
+            output!(
+                "{}",
+                commands::dig(&project, &fname, &record, observation, json)?
+            );
+            Ok(())
+        }
+        Cmd::Compare {
+            fossil: fname,
+            variants,
+            last,
+            analysis,
+            script,
+            test,
+            alpha,
+        } => {
+            let project = Project::resolve(
+                &projects_dir,
+                cli.project.as_deref(),
+                Some(&fname),
+            )?;
+            let columns = commands::compare(
+                &project,
+                &fname,
+                &variants,
+                last,
+                analysis.as_deref(),
+                &script.into(),
+            )?;
+            output!("{}", analysis::Comparison::new(&columns, test, alpha));
+            Ok(())
+        }
+        Cmd::Cache { command } => match command {
+            CacheCmd::Purge { fossil } => {
+                let project = Project::resolve(
+                    &projects_dir,
+                    cli.project.as_deref(),
+                    fossil.as_deref(),
+                )?;
+                let (records, bytes) =
+                    commands::purge_cache(&project, fossil.as_deref())?;
+                status!(
+                    "purged {} of cached analysis from {records} records",
+                    ByteSize(bytes)
+                );
+                Ok(())
+            }
+        },
+        Cmd::Check {
+            fossil: fname,
+            baseline,
+            baseline_tag,
+            baseline_branch,
+            variants,
+            analysis,
+            script,
+            test,
+            alpha,
+        } => {
+            let project = Project::resolve(
+                &projects_dir,
+                cli.project.as_deref(),
+                Some(&fname),
+            )?;
+            // clap requires exactly one of the three.
+            let baseline = match (baseline, baseline_tag, baseline_branch) {
+                (Some(r), ..) => commands::Baseline::Record(r),
+                (_, Some(t), _) => commands::Baseline::Tag(t),
+                (.., Some(b)) => commands::Baseline::Branch(b),
+                _ => unreachable!(),
+            };
+            let opts = commands::CheckOptions {
+                variants,
+                analysis,
+                test,
+                alpha,
+                script: script.into(),
+            };
+            let check = commands::check(&project, &fname, &baseline, &opts)?;
+            output!("{check}");
+            match check.failures() {
+                0 => Ok(()),
+                n => Err(error::FossilError::CheckFailed(n)),
+            }
+        }
+        Cmd::Trend {
+            spec,
+            metric,
+            order,
+            last,
+            analysis,
+            script,
+            test,
+            alpha,
+        } => {
+            let fname = spec.split(':').next().unwrap_or(&spec);
+            let project = Project::resolve(
+                &projects_dir,
+                cli.project.as_deref(),
+                Some(fname),
+            )?;
+            let (metric, points) = commands::trend(
+                &project,
+                &spec,
+                metric.as_deref(),
+                order,
+                last,
+                analysis.as_deref(),
+                &script.into(),
             )?;
-            output!("{}", analysis::columns_to_json(&columns)?);
+            output!("{}", analysis::Trend::new(metric, points, test, alpha));
             Ok(())
         }
         Cmd::Figure {
@@ -189,6 +339,7 @@ fn run() -> Result<(), error::FossilError> {
             last,
             variant,
             figure: fig_name,
+            script,
         } => {
             let project = Project::resolve(
                 &projects_dir,
@@ -207,6 +358,7 @@ fn run() -> Result<(), error::FossilError> {
                 &[spec],
                 last,
                 Some(fig.analysis_name()),
+                &script.into(),
             )?;
             fig.run(&f, &columns)?;
             figure::Figure::open(&fig.output_path(&f));
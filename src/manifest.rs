@@ -1,8 +1,11 @@
-use crate::environment::{CpuInfo, GitInfo};
+use crate::artifact::{Artifact, ByteSize};
+use crate::command::CommandSpec;
+use crate::environment::{CpuInfo, GitInfo, MachineInfo, RunEnv};
 use crate::error::FossilError;
-use crate::fossil::{Fossil, FossilVariantKey};
+use crate::fossil::{Fossil, FossilVariantKey, MatrixParams};
 use crate::project::Project;
-use crate::runner::{Results, Run};
+use crate::runner::{Convergence, Results, Run};
+use crate::schedule::Schedule;
 
 use chrono::Local;
 use serde::{Deserialize, Serialize};
@@ -11,21 +14,49 @@ use std::path::{Path, PathBuf};
 /// [Fossil Doc] `Manifest`
 /// -------------------------------------------------------------
 /// Metadata snapshot captured at bury-time. Records what was run,
-/// which variant, the git state, CPU config, and kernel version.
-/// Stored as manifest.json alongside the results.
+/// which variant, the git state, CPU config, kernel version and the
+/// machine it ran on. Stored as manifest.json alongside the results.
+///
+/// Version 4 added `machine`, the source repo's full git state,
+/// matrix `params`, the interleaving `schedule`, adaptive
+/// `convergence`, collected `artifacts`, the run's `env` and
+/// free-form `tags`. Version 3 manifests still load, with those
+/// fields left empty.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Manifest {
     pub version: u32,
     pub timestamp: String,
     pub fossil: String,
     pub project: String,
-    pub command: String,
+    pub command: CommandSpec,
     pub description: Option<String>,
     pub iterations: u32,
+    #[serde(default)]
+    pub warmup: u32,
     pub variant: Option<FossilVariantKey>,
+    /// Matrix parameters of the variant, for grouping by axis.
+    #[serde(default, skip_serializing_if = "MatrixParams::is_empty")]
+    pub params: MatrixParams,
     pub git: GitInfo,
     pub cpu: CpuInfo,
     pub kernel: String,
+    #[serde(default)]
+    pub machine: Option<MachineInfo>,
+    /// How this run was interleaved with other variants, if it was.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<Schedule>,
+    /// Why an adaptive run stopped at `iterations`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub convergence: Option<Convergence>,
+    /// Files collected from the workdir into `artifacts/`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<Artifact>,
+    /// The environment the run saw, secrets redacted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<RunEnv>,
+    /// Labels given with `bury --tag`, for finding runs later.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
 }
 
 impl Manifest {
@@ -35,22 +66,92 @@ impl Manifest {
         run: &Run,
         git: GitInfo,
         cpu: CpuInfo,
-    ) -> Self {
-        Self {
-            version: 3,
+        machine: MachineInfo,
+    ) -> Result<Self, FossilError> {
+        Ok(Self {
+            // Bump with a note in the Manifest doc when fields change.
+            version: 4,
             timestamp: Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
             fossil: fossil.config.name.clone(),
             project: project.config.name.clone(),
             command: run.command.clone(),
             description: fossil.config.description.clone(),
             iterations: run.iterations,
+            warmup: run.warmup,
             variant: run.variant.clone(),
+            params: run.params.clone(),
             git,
             cpu,
             kernel: std::fs::read_to_string("/proc/sys/kernel/osrelease")
                 .map(|s| s.trim().to_string())
                 .unwrap_or_else(|_| "unknown".into()),
+            machine: Some(machine),
+            schedule: None,
+            convergence: run.convergence.clone(),
+            artifacts: run.artifacts.collected.clone(),
+            env: Some(run.env.redacted(&fossil.config.redact_env())?),
+            tags: run.tags.clone(),
+        })
+    }
+
+    /// Human readable (label, value) pairs, shared by `fossil dig`
+    /// and the TUI preview.
+    pub fn summary(&self) -> Vec<(&'static str, String)> {
+        let mut lines = vec![
+            ("fossil", self.fossil.clone()),
+            ("project", self.project.clone()),
+            ("timestamp", self.timestamp.clone()),
+            (
+                "variant",
+                self.variant
+                    .as_ref()
+                    .map(|v| v.to_string())
+                    .unwrap_or_else(|| "-".into()),
+            ),
+            ("command", self.command.to_string()),
+            ("iterations", self.iterations.to_string()),
+            ("git", self.git.describe()),
+            ("cpu", self.cpu.describe()),
+            ("kernel", self.kernel.clone()),
+        ];
+        if !self.tags.is_empty() {
+            lines.push(("tags", self.tags.join(", ")));
+        }
+        if let Some(repo) = &self.git.repo {
+            lines.insert(6, ("source", repo.display().to_string()));
+        }
+        if let Some(c) = &self.convergence {
+            lines.push(("stopped", c.describe()));
+        }
+        if !self.artifacts.is_empty() {
+            let size = self.artifacts.iter().map(|a| a.size).sum();
+            let mut line =
+                format!("{} files, {}", self.artifacts.len(), ByteSize(size));
+            let ignored =
+                self.artifacts.iter().filter(|a| !a.committed).count();
+            if ignored > 0 {
+                line.push_str(&format!(" ({ignored} not in git)"));
+            }
+            lines.push(("artifacts", line));
+        }
+        if let Some(env) = &self.env {
+            lines.push(("env", env.describe()));
         }
+        if let Some(s) = &self.schedule {
+            lines.push(("order", s.describe()));
+        }
+        if let Some(m) = &self.machine {
+            lines.push(("host", m.hostname.clone()));
+            lines.push(("machine", m.describe()));
+            if let Some([l1, l5, l15]) = m.load_avg {
+                lines.push(("load", format!("{l1:.2} {l5:.2} {l15:.2}")));
+            }
+            lines.push(("libc", m.libc.clone()));
+        }
+        if self.warmup > 0 {
+            lines.insert(6, ("warmup", self.warmup.to_string()));
+        }
+        lines
     }
 
     pub fn load(run_dir: &Path) -> Result<Self, FossilError> {
@@ -60,10 +161,13 @@ impl Manifest {
         )
     }
 
+    /// Write the record to disk. `patch` is the source tree's
+    /// uncommitted diff, saved as source.patch when there is one.
     pub fn record(
         &self,
         records_dir: &Path,
         results: &Results,
+        patch: Option<&str>,
     ) -> Result<PathBuf, FossilError> {
         let ts = Local::now().format("%Y%m%d_%H%M%S_%3f");
         let mut parts = vec![ts.to_string()];
@@ -92,6 +196,10 @@ impl Manifest {
             })?;
         std::fs::write(run_dir.join("results.json"), results_json + "\n")?;
 
+        if let Some(patch) = patch {
+            std::fs::write(run_dir.join("source.patch"), patch)?;
+        }
+
         Ok(run_dir)
     }
 }
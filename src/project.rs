@@ -198,7 +198,12 @@ impl Project {
     pub fn delete_record(&self, record: &Record) -> Result<(), FossilError> {
         let rel = self.rel_path(&record.dir)?;
         git::Repo::at(&self.path)
-            .rm(&rel, format!("delete record {}", record.id()))
+            .rm(&rel, format!("delete record {}", record.id()))?;
+        // git leaves ignored files behind, such as cached analysis.
+        if record.dir.exists() {
+            std::fs::remove_dir_all(&record.dir)?;
+        }
+        Ok(())
     }
 
     pub fn import(&self, toml_path: &Path) -> Result<(), FossilError> {
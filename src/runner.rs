@@ -1,65 +1,452 @@
+use crate::artifact::{Artifacts, Per};
+use crate::command::CommandSpec;
+use crate::environment::{CpuSet, RunEnv};
 use crate::error::FossilError;
-use crate::fossil::FossilVariantKey;
+use crate::fossil::{FossilVariantKey, Hooks, MatrixParams};
+use crate::io::{status, warning};
 use serde::{Deserialize, Serialize};
 use std::io::{BufRead, BufReader, Read};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
 use std::path::{Path, PathBuf};
-use std::process::Command as ProcessCommand;
-use std::time::Instant;
+use std::process::ExitStatus;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Results {
+    /// Iterations run before measurement to warm caches, JITs and
+    /// build outputs. Kept for inspection, ignored by analysis.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warmup: Vec<Observation>,
     pub observations: Vec<Observation>,
+    /// Every hook that ran, in order, outside the timed region.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hooks: Vec<HookRun>,
+}
+
+impl Results {
+    pub fn load(run_dir: &Path) -> Result<Self, FossilError> {
+        let raw = std::fs::read_to_string(run_dir.join("results.json"))?;
+        serde_json::from_str(&raw).map_err(|e| {
+            FossilError::InvalidConfig(format!(
+                "corrupt data in {}: {e}",
+                run_dir.display()
+            ))
+        })
+    }
+}
+
+/// How an iteration ended.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ObservationStatus {
+    /// The command exited on its own; see `exit_code`.
+    #[default]
+    Exited,
+    /// The command was killed by a signal it did not send itself.
+    Signaled,
+    /// The command outlived its timeout and fossil killed it.
+    TimedOut,
+}
+
+/// Which of a fossil's hooks ran.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookKind {
+    Setup,
+    Teardown,
+    BeforeEach,
+    AfterEach,
+}
+
+impl HookKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Setup => "setup",
+            Self::Teardown => "teardown",
+            Self::BeforeEach => "before_each",
+            Self::AfterEach => "after_each",
+        }
+    }
+
+    /// Cleanup hooks only warn on failure; the measurements they
+    /// follow are already taken.
+    fn is_cleanup(&self) -> bool {
+        matches!(self, Self::Teardown | Self::AfterEach)
+    }
+}
+
+/// One execution of a hook. `iteration` is the iteration it
+/// surrounded, or 0 for setup and teardown.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookRun {
+    pub hook: HookKind,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub warmup: bool,
+    #[serde(flatten)]
+    pub observation: Observation,
+}
+
+/// [Fossil Doc] `Progress`
+/// -------------------------------------------------------------
+/// Events a Run reports while it executes, for a front end that
+/// shows more than the `[fossil]` status lines, e.g. the TUI's run
+/// panel. Sent over `Run::progress` when one is attached.
+#[derive(Debug, Clone)]
+pub enum Progress {
+    /// An iteration is starting; `total` is the planned count, or
+    /// the cap for adaptive runs.
+    Started {
+        variant: String,
+        warmup: bool,
+        iteration: u32,
+        total: u32,
+    },
+    /// A hook is starting.
+    Hook { variant: String, hook: HookKind },
+    /// A line the command or hook wrote.
+    Output { stderr: bool, line: String },
+    /// Something the CLI would print as `warning:`, such as an
+    /// artifact skipped for size or a cleanup hook that failed.
+    Warning(String),
+    /// An iteration ended.
+    Finished {
+        warmup: bool,
+        iteration: u32,
+        wall_time_us: u64,
+        outcome: String,
+    },
+}
+
+/// Report `message` as a `Progress::Warning` when something is
+/// listening, e.g. the TUI, whose frame stderr would tear, and print
+/// it as a `warning:` line otherwise.
+pub fn warn(progress: Option<&mpsc::Sender<Progress>>, message: String) {
+    match progress {
+        Some(tx) => {
+            let _ = tx.send(Progress::Warning(message));
+        }
+        None => warning!("{message}"),
+    }
+}
+
+/// Why an adaptive run stopped iterating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    /// The confidence interval narrowed to the target.
+    Converged,
+    /// `max_iterations` ran out first.
+    MaxIterations,
+}
+
+/// Outcome of an adaptive run, as recorded in the manifest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Convergence {
+    pub metric: String,
+    pub confidence: f64,
+    pub target: f64,
+    /// Relative CI half-width of `metric` after the last iteration.
+    pub achieved: Option<f64>,
+    pub stop: StopReason,
+}
+
+impl Convergence {
+    /// Short form for `fossil dig`, e.g. `converged at ±1.8%`.
+    pub fn describe(&self) -> String {
+        let achieved = self
+            .achieved
+            .map(|a| format!("±{:.2}%", a * 100.0))
+            .unwrap_or_else(|| "n/a".into());
+        let stop = match self.stop {
+            StopReason::Converged => "converged",
+            StopReason::MaxIterations => "hit max_iterations",
+        };
+        format!(
+            "{stop} at {achieved} (target ±{:.2}% of {}, {}% CI)",
+            self.target * 100.0,
+            self.metric,
+            self.confidence
+        )
+    }
 }
 
 /// [Fossil Doc] `Observation`
 /// -------------------------------------------------------------
 /// A single iteration of running the command. Captures stdout,
-/// stderr, exit code, and wall time. A Record contains many of
+/// stderr, exit status, and wall time. A Record contains many of
 /// these, one per iteration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Observation {
     pub iteration: u32,
     pub wall_time_us: u64,
-    pub exit_code: i32,
+    #[serde(default)]
+    pub status: ObservationStatus,
+    /// `None` unless the command exited on its own.
+    pub exit_code: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal: Option<i32>,
+    /// Resource usage of the command and every descendant it waited
+    /// for. Absent in records from before it was collected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rusage: Option<ResourceUsage>,
     pub stdout: Vec<String>,
     pub stderr: Vec<String>,
 }
 
+/// [Fossil Doc] `ResourceUsage`
+/// -------------------------------------------------------------
+/// What the kernel accounted to one iteration, as reported by
+/// `wait4(2)`. Saves wrapping commands in `/usr/bin/time -v` and
+/// scraping stderr for the same numbers.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    pub user_time_us: u64,
+    pub system_time_us: u64,
+    pub max_rss_kb: u64,
+    pub minor_faults: u64,
+    pub major_faults: u64,
+    pub voluntary_ctx_switches: u64,
+    pub involuntary_ctx_switches: u64,
+    pub block_input: u64,
+    pub block_output: u64,
+}
+
+impl ResourceUsage {
+    fn from_raw(ru: &libc::rusage) -> Self {
+        let micros = |tv: libc::timeval| {
+            tv.tv_sec as u64 * 1_000_000 + tv.tv_usec as u64
+        };
+        Self {
+            user_time_us: micros(ru.ru_utime),
+            system_time_us: micros(ru.ru_stime),
+            // Linux reports ru_maxrss in kilobytes, macOS in bytes.
+            max_rss_kb: if cfg!(target_os = "macos") {
+                ru.ru_maxrss as u64 / 1024
+            } else {
+                ru.ru_maxrss as u64
+            },
+            minor_faults: ru.ru_minflt as u64,
+            major_faults: ru.ru_majflt as u64,
+            voluntary_ctx_switches: ru.ru_nvcsw as u64,
+            involuntary_ctx_switches: ru.ru_nivcsw as u64,
+            block_input: ru.ru_inblock as u64,
+            block_output: ru.ru_oublock as u64,
+        }
+    }
+
+    pub fn cpu_time_us(&self) -> u64 {
+        self.user_time_us + self.system_time_us
+    }
+}
+
+/// Reap `pid` with `wait4(2)`, which unlike `Child::wait` also hands
+/// back the child's resource usage.
+fn wait_with_rusage(pid: i32) -> std::io::Result<(ExitStatus, ResourceUsage)> {
+    let mut status = 0;
+    // SAFETY: rusage is plain old data, so all-zeroes is a valid value.
+    let mut ru: libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        // SAFETY: both out-pointers are valid for the whole call.
+        let ret = unsafe { libc::wait4(pid, &mut status, 0, &mut ru) };
+        if ret == pid {
+            break;
+        }
+        let err = std::io::Error::last_os_error();
+        if err.kind() != std::io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+    Ok((ExitStatus::from_raw(status), ResourceUsage::from_raw(&ru)))
+}
+
+/// Everything about how a command is launched besides the command.
+struct Launch<'a> {
+    workdir: Option<&'a Path>,
+    env: &'a RunEnv,
+    timeout: Option<Duration>,
+    cpus: Option<&'a CpuSet>,
+    silent: bool,
+    progress: Option<&'a mpsc::Sender<Progress>>,
+    cancel: Option<&'a Arc<AtomicBool>>,
+}
+
+/// Time between SIGTERM and SIGKILL when a command times out.
+const KILL_GRACE: Duration = Duration::from_secs(2);
+
 impl Observation {
     fn run(
-        command: &str,
+        command: &CommandSpec,
         iteration: u32,
-        workdir: Option<&Path>,
-        silent: bool,
+        launch: &Launch,
     ) -> Result<Self, FossilError> {
-        let mut cmd = ProcessCommand::new("sh");
-        cmd.args(["-c", command]);
+        let mut cmd = command.to_process()?;
+        launch.env.apply(&mut cmd);
         cmd.stdout(std::process::Stdio::piped());
         cmd.stderr(std::process::Stdio::piped());
-        if let Some(dir) = workdir {
+        // Own process group, so a timeout takes down everything the
+        // command spawned and not just the `sh` wrapper.
+        cmd.process_group(0);
+        if let Some(dir) = launch.workdir {
             cmd.current_dir(dir);
         }
+        if let Some(cpus) = launch.cpus {
+            cpus.pin(&mut cmd);
+        }
 
         let start = Instant::now();
         let mut child = cmd.spawn()?;
+        let pgid = child.id() as i32;
 
-        let echo = !silent;
-        let stdout_handle =
-            drain_lines(child.stdout.take().unwrap(), echo, false);
-        let stderr_handle =
-            drain_lines(child.stderr.take().unwrap(), echo, true);
+        let echo = !launch.silent;
+        let stdout_handle = drain_lines(
+            child.stdout.take().unwrap(),
+            echo,
+            false,
+            launch.progress.cloned(),
+        );
+        let stderr_handle = drain_lines(
+            child.stderr.take().unwrap(),
+            echo,
+            true,
+            launch.progress.cloned(),
+        );
 
-        let status = child.wait()?;
+        let watchdog = (launch.timeout.is_some() || launch.cancel.is_some())
+            .then(|| {
+                Watchdog::arm(pgid, launch.timeout, launch.cancel.cloned())
+            });
+        // The child is reaped here rather than through `child`, which
+        // is only kept around for its pipes.
+        let (status, rusage) = wait_with_rusage(child.id() as i32)?;
         let wall_time_us = start.elapsed().as_micros() as u64;
+        let trip = watchdog.and_then(Watchdog::disarm);
+        if trip.is_some() {
+            // The leader is gone, but stragglers that ignored SIGTERM
+            // could still hold our pipes open.
+            kill_group(pgid, libc::SIGKILL);
+        }
+        if trip == Some(Trip::Cancelled) {
+            let _ = stdout_handle.join();
+            let _ = stderr_handle.join();
+            return Err(FossilError::Cancelled);
+        }
+        let timed_out = trip == Some(Trip::TimedOut);
+
+        let status_kind = if timed_out {
+            ObservationStatus::TimedOut
+        } else if status.code().is_some() {
+            ObservationStatus::Exited
+        } else {
+            ObservationStatus::Signaled
+        };
 
         Ok(Self {
             iteration,
             wall_time_us,
-            exit_code: status.code().unwrap_or(-1),
+            status: status_kind,
+            exit_code: status.code().filter(|_| !timed_out),
+            signal: status.signal().filter(|_| !timed_out),
+            rusage: Some(rusage),
             stdout: stdout_handle.join().unwrap_or_default(),
             stderr: stderr_handle.join().unwrap_or_default(),
         })
     }
+
+    pub fn succeeded(&self) -> bool {
+        self.status == ObservationStatus::Exited && self.exit_code == Some(0)
+    }
+
+    /// Short description of how the iteration ended, e.g. `exit 0`,
+    /// `signal 9`, `timeout`.
+    pub fn outcome(&self) -> String {
+        match self.status {
+            ObservationStatus::Exited => {
+                format!("exit {}", self.exit_code.unwrap_or(-1))
+            }
+            ObservationStatus::Signaled => {
+                format!("signal {}", self.signal.unwrap_or(-1))
+            }
+            ObservationStatus::TimedOut => "timeout".into(),
+        }
+    }
+}
+
+/// How often the watchdog checks for a cancel request.
+const CANCEL_POLL: Duration = Duration::from_millis(50);
+
+/// Why a watchdog killed its process group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Trip {
+    TimedOut,
+    Cancelled,
+}
+
+/// Kills a process group once its deadline passes or `cancel` is
+/// set: SIGTERM first, then SIGKILL if it is still around after
+/// `KILL_GRACE`.
+struct Watchdog {
+    done: mpsc::Sender<()>,
+    handle: std::thread::JoinHandle<Option<Trip>>,
+}
+
+impl Watchdog {
+    fn arm(
+        pgid: i32,
+        timeout: Option<Duration>,
+        cancel: Option<Arc<AtomicBool>>,
+    ) -> Self {
+        let (done, rx) = mpsc::channel::<()>();
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let handle = std::thread::spawn(move || {
+            let trip = loop {
+                let now = Instant::now();
+                let wait = match (deadline, &cancel) {
+                    (Some(d), None) => d.saturating_duration_since(now),
+                    (Some(d), Some(_)) => {
+                        d.saturating_duration_since(now).min(CANCEL_POLL)
+                    }
+                    (None, _) => CANCEL_POLL,
+                };
+                if rx.recv_timeout(wait) != Err(RecvTimeoutError::Timeout) {
+                    return None;
+                }
+                if cancel
+                    .as_ref()
+                    .is_some_and(|c| c.load(Ordering::SeqCst))
+                {
+                    break Trip::Cancelled;
+                }
+                if deadline.is_some_and(|d| Instant::now() >= d) {
+                    break Trip::TimedOut;
+                }
+            };
+            kill_group(pgid, libc::SIGTERM);
+            if rx.recv_timeout(KILL_GRACE) == Err(RecvTimeoutError::Timeout) {
+                kill_group(pgid, libc::SIGKILL);
+            }
+            Some(trip)
+        });
+        Self { done, handle }
+    }
+
+    /// Stop watching; returns why the group was killed, if it was.
+    fn disarm(self) -> Option<Trip> {
+        let _ = self.done.send(());
+        self.handle.join().unwrap_or(None)
+    }
+}
+
+fn kill_group(pgid: i32, signal: i32) {
+    // SAFETY: kill(2) has no memory-safety preconditions; a stale
+    // group id just yields ESRCH.
+    unsafe {
+        libc::kill(-pgid, signal);
+    }
 }
 
 /// [Fossil Doc] `Run`
@@ -68,34 +455,201 @@ impl Observation {
 /// observations collected so far. Once finished, a Run becomes
 /// a Record on disk.
 pub struct Run {
-    pub command: String,
+    pub command: CommandSpec,
     pub iterations: u32,
+    pub warmup: u32,
     pub variant: Option<FossilVariantKey>,
+    pub params: MatrixParams,
+    pub hooks: Hooks,
     pub allow_failure: bool,
     pub workdir: Option<PathBuf>,
+    pub env: RunEnv,
+    pub tags: Vec<String>,
+    pub timeout: Option<Duration>,
+    pub cpus: Option<CpuSet>,
     pub silent: bool,
+    /// Where to report progress, if anyone is listening.
+    pub progress: Option<mpsc::Sender<Progress>>,
+    /// Set from another thread to kill the current iteration and
+    /// abandon the run.
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// Set once an adaptive run decides to stop.
+    pub convergence: Option<Convergence>,
+    pub warmups: Vec<Observation>,
     pub observations: Vec<Observation>,
+    pub hook_runs: Vec<HookRun>,
+    pub artifacts: Artifacts,
 }
 
 impl Run {
     pub fn execute_one(&mut self) -> Result<&Observation, FossilError> {
+        self.check_cancelled()?;
         let i = self.observations.len() as u32 + 1;
-        let workdir = self.workdir.as_deref();
-        let obs = Observation::run(&self.command, i, workdir, self.silent)?;
-        if obs.exit_code != 0 && !self.allow_failure {
-            return Err(FossilError::CommandFailed {
-                command: self.command.clone(),
-                iteration: i,
-                exit_code: obs.exit_code,
-            });
-        }
+        self.hook(HookKind::BeforeEach, i, false)?;
+        let obs = self.observe(i)?;
         self.observations.push(obs);
+        // Before after_each, which may well clean them up.
+        self.collect_artifacts(Per::Iteration, Some(i))?;
+        self.hook(HookKind::AfterEach, i, false)?;
         Ok(self.observations.last().unwrap())
     }
 
+    pub fn execute_warmup(&mut self) -> Result<&Observation, FossilError> {
+        self.check_cancelled()?;
+        let i = self.warmups.len() as u32 + 1;
+        self.hook(HookKind::BeforeEach, i, true)?;
+        let obs = self.observe(i)?;
+        self.warmups.push(obs);
+        self.hook(HookKind::AfterEach, i, true)?;
+        Ok(self.warmups.last().unwrap())
+    }
+
+    /// Copy the `per` artifacts out of the workdir. Per-run ones are
+    /// collected after the last iteration, before teardown.
+    pub fn collect_artifacts(
+        &mut self,
+        per: Per,
+        iteration: Option<u32>,
+    ) -> Result<(), FossilError> {
+        let workdir = match &self.workdir {
+            Some(dir) => dir.clone(),
+            None => std::env::current_dir()?,
+        };
+        self.artifacts
+            .collect(per, iteration, &workdir, self.progress.as_ref())
+    }
+
+    /// Run a hook, if the variant has one, and log it. A failing
+    /// setup or before_each aborts the run unless failures are
+    /// allowed; the cleanup hooks only warn, even when they can't be
+    /// started at all.
+    pub fn hook(
+        &mut self,
+        kind: HookKind,
+        iteration: u32,
+        warmup: bool,
+    ) -> Result<(), FossilError> {
+        let command = match kind {
+            HookKind::Setup => &self.hooks.setup,
+            HookKind::Teardown => &self.hooks.teardown,
+            HookKind::BeforeEach => &self.hooks.before_each,
+            HookKind::AfterEach => &self.hooks.after_each,
+        };
+        let Some(command) = command.clone() else {
+            return Ok(());
+        };
+        if !self.silent && matches!(kind, HookKind::Setup | HookKind::Teardown)
+        {
+            status!("{}: {command}", kind.name());
+        }
+        self.report(Progress::Hook {
+            variant: self.label().to_string(),
+            hook: kind,
+        });
+        // Hooks run to completion even when cancelled: teardown has
+        // to clean up after whatever was interrupted.
+        let launch = Launch {
+            timeout: None,
+            cpus: None,
+            cancel: None,
+            ..self.launch()
+        };
+        let obs = match Observation::run(&command, iteration, &launch) {
+            Ok(obs) => obs,
+            Err(e) if kind.is_cleanup() => {
+                self.warn(format!("{} hook failed to start: {e}", kind.name()));
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        let ok = obs.succeeded();
+        let outcome = obs.outcome();
+        self.hook_runs.push(HookRun {
+            hook: kind,
+            warmup,
+            observation: obs,
+        });
+        if ok {
+            return Ok(());
+        }
+        if kind.is_cleanup() || self.allow_failure {
+            self.warn(format!("{} hook failed ({outcome})", kind.name()));
+            return Ok(());
+        }
+        Err(FossilError::HookFailed {
+            hook: kind.name(),
+            command: command.to_string(),
+            outcome,
+        })
+    }
+
+    fn observe(&self, i: u32) -> Result<Observation, FossilError> {
+        let obs = Observation::run(&self.command, i, &self.launch())?;
+        if obs.succeeded() || self.allow_failure {
+            return Ok(obs);
+        }
+        Err(match (obs.status, self.timeout) {
+            (ObservationStatus::TimedOut, Some(t)) => FossilError::TimedOut {
+                command: self.command.to_string(),
+                iteration: i,
+                seconds: t.as_secs_f64(),
+            },
+            _ => FossilError::CommandFailed {
+                command: self.command.to_string(),
+                iteration: i,
+                outcome: obs.outcome(),
+            },
+        })
+    }
+
+    pub fn label(&self) -> &str {
+        self.variant
+            .as_ref()
+            .map_or("untagged", |v| v.as_str())
+    }
+
+    /// Send `event` to the progress listener, if there is one. A
+    /// listener that went away is not the run's problem.
+    pub fn report(&self, event: Progress) {
+        if let Some(tx) = &self.progress {
+            let _ = tx.send(event);
+        }
+    }
+
+    pub fn warn(&self, message: String) {
+        warn(self.progress.as_ref(), message);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|c| c.load(Ordering::SeqCst))
+    }
+
+    fn check_cancelled(&self) -> Result<(), FossilError> {
+        if self.is_cancelled() {
+            return Err(FossilError::Cancelled);
+        }
+        Ok(())
+    }
+
+    fn launch(&self) -> Launch<'_> {
+        Launch {
+            workdir: self.workdir.as_deref(),
+            env: &self.env,
+            timeout: self.timeout,
+            cpus: self.cpus.as_ref(),
+            silent: self.silent,
+            progress: self.progress.as_ref(),
+            cancel: self.cancel.as_ref(),
+        }
+    }
+
     pub fn results(&self) -> Results {
         Results {
+            warmup: self.warmups.clone(),
             observations: self.observations.clone(),
+            hooks: self.hook_runs.clone(),
         }
     }
 }
@@ -104,6 +658,7 @@ fn drain_lines(
     stream: impl Read + Send + 'static,
     echo: bool,
     to_stderr: bool,
+    progress: Option<mpsc::Sender<Progress>>,
 ) -> std::thread::JoinHandle<Vec<String>> {
     std::thread::spawn(move || {
         BufReader::new(stream)
@@ -117,6 +672,12 @@ fn drain_lines(
                         println!("{l}");
                     }
                 }
+                if let Some(tx) = &progress {
+                    let _ = tx.send(Progress::Output {
+                        stderr: to_stderr,
+                        line: l.clone(),
+                    });
+                }
             })
             .collect()
     })
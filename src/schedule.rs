@@ -0,0 +1,129 @@
+use crate::fossil::FossilVariantKey;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The order in which `bury` works through the measured iterations
+/// of a fossil's variants when burying all of them.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Order {
+    /// Every iteration of one variant, then the next variant.
+    #[default]
+    Sequential,
+    /// One iteration of each variant per round.
+    RoundRobin,
+    /// All (variant, iteration) pairs in a seeded random order.
+    Shuffle,
+}
+
+/// [Fossil Doc] `Schedule`
+/// -------------------------------------------------------------
+/// The interleaving a multi-variant bury actually ran. Spreading
+/// each variant's iterations across the whole run keeps thermal
+/// drift and background load from lining up with one variant.
+/// Stored in every manifest of the run; with the seed, a shuffle
+/// can be replayed exactly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    pub order: Order,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    pub variants: Vec<FossilVariantKey>,
+    /// Index into `variants` of each measured iteration, in the order
+    /// they ran. Warmups all run before it.
+    pub sequence: Vec<usize>,
+}
+
+impl Schedule {
+    /// Lay out `iterations[i]` iterations of `variants[i]`. A shuffle
+    /// without a seed picks one from the clock and records it.
+    pub fn new(
+        order: Order,
+        seed: Option<u64>,
+        variants: Vec<FossilVariantKey>,
+        iterations: &[u32],
+    ) -> Self {
+        let mut sequence: Vec<usize> = match order {
+            Order::RoundRobin => {
+                let rounds = iterations.iter().copied().max().unwrap_or(0);
+                (0..rounds)
+                    .flat_map(|round| {
+                        iterations
+                            .iter()
+                            .enumerate()
+                            .filter(move |&(_, &n)| round < n)
+                            .map(|(i, _)| i)
+                    })
+                    .collect()
+            }
+            Order::Sequential | Order::Shuffle => iterations
+                .iter()
+                .enumerate()
+                .flat_map(|(i, &n)| std::iter::repeat_n(i, n as usize))
+                .collect(),
+        };
+        let seed = (order == Order::Shuffle).then(|| {
+            seed.unwrap_or_else(|| {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_nanos() as u64)
+                    .unwrap_or_default()
+            })
+        });
+        if let Some(seed) = seed {
+            let mut rng = SplitMix64(seed);
+            for i in (1..sequence.len()).rev() {
+                sequence.swap(i, rng.below(i + 1));
+            }
+        }
+        Self {
+            order,
+            seed,
+            variants,
+            sequence,
+        }
+    }
+
+    /// Short form for `fossil dig`, e.g. `shuffle (seed 42)`.
+    pub fn describe(&self) -> String {
+        let order = match self.order {
+            Order::Sequential => "sequential",
+            Order::RoundRobin => "round-robin",
+            Order::Shuffle => "shuffle",
+        };
+        match self.seed {
+            Some(seed) => format!("{order} (seed {seed})"),
+            None => order.to_string(),
+        }
+    }
+}
+
+/// SplitMix64. Small and fully specified, so a seed stored in a
+/// manifest reproduces the same shuffle in any later fossil build,
+/// which a general-purpose RNG crate does not promise.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..bound`.
+    fn below(&mut self, bound: usize) -> usize {
+        ((self.next() as u128 * bound as u128) >> 64) as usize
+    }
+}
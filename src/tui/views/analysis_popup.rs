@@ -1,4 +1,3 @@
-use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::sync::mpsc;
 use std::time::Instant;
@@ -8,6 +7,7 @@ use crossterm::event::KeyEvent;
 use ratatui::Frame;
 use ratatui::layout::Rect;
 
+use crate::analysis::{ScriptOptions, SummaryField};
 use crate::commands;
 use crate::entity::DirEntity;
 use crate::fossil::Fossil;
@@ -25,10 +25,11 @@ struct LoadingState {
     start: Instant,
 }
 
-fn format_metrics(cols: &[(String, crate::analysis::Metric)]) -> String {
-    let map: BTreeMap<&str, &crate::analysis::Metric> =
-        cols.iter().map(|(n, m)| (n.as_str(), m)).collect();
-    serde_json::to_string_pretty(&map).unwrap_or_default()
+fn format_metrics(
+    cols: &[(String, crate::analysis::Metric)],
+    fields: &[SummaryField],
+) -> String {
+    crate::analysis::columns_to_json(cols, fields).unwrap_or_default()
 }
 
 pub struct AnalysisPopupState {
@@ -96,6 +97,7 @@ impl AnalysisPopupState {
             let project_path = self.project_path.clone();
             let fossil_name = self.fossil.config.name.clone();
             let analysis_name = name.clone();
+            let fields = self.fossil.config.summary_fields().to_vec();
             std::thread::spawn(move || {
                 let result = Project::load(&project_path).and_then(|project| {
                     commands::analyze(
@@ -103,11 +105,12 @@ impl AnalysisPopupState {
                         &[fossil_name],
                         None,
                         Some(&analysis_name),
+                        &ScriptOptions::default(),
                     )
                 });
                 let _ = tx.send(match result {
                     Ok(cols) => {
-                        let s = format_metrics(&cols);
+                        let s = format_metrics(&cols, &fields);
                         Ok((s, cols))
                     }
                     Err(e) => Err(e.to_string()),
@@ -136,7 +139,8 @@ impl AnalysisPopupState {
                         }
                     }
                 }
-                let _ = tx.send(Ok((format_metrics(&cols), cols)));
+                let fields = fossil.config.summary_fields();
+                let _ = tx.send(Ok((format_metrics(&cols, fields), cols)));
             });
         }
 
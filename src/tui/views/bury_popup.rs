@@ -1,91 +1,136 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::PathBuf;
-use std::sync::mpsc;
 
-use crate::commands;
-use crate::entity::DirEntity;
 use crate::fossil::{Fossil, FossilVariantKey};
-use crate::project::Project;
-use crossterm::event::KeyEvent;
+use crate::tui::theme;
+use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::Frame;
 use ratatui::layout::Rect;
 
+use super::run_panel::BuryJob;
 use super::{ListEntry, SelectorAction, SelectorPopup};
 
+/// The first entry, standing for every variant.
+const ALL: usize = 0;
+
 pub struct BuryPopupState {
+    fossil_name: String,
     fossil_path: PathBuf,
     project_path: PathBuf,
     variants: Vec<FossilVariantKey>,
+    /// Entries marked with space, `ALL` included.
+    marked: BTreeSet<usize>,
+    /// Digits typed so far, overriding the iteration count.
+    iterations: String,
     selector: SelectorPopup,
 }
 
 pub enum BuryAction {
     None,
     Dismiss,
-    Started(String, mpsc::Receiver<Result<String, String>>),
+    Queue(BuryJob),
 }
 
 impl BuryPopupState {
     pub fn new(fossil: &Fossil, project_path: PathBuf) -> Self {
-        let variants: Vec<FossilVariantKey> =
-            fossil.config.variants.keys().cloned().collect();
-        let entries: Vec<ListEntry> = variants
-            .iter()
-            .map(|vn| {
+        let variants = fossil.config.variant_keys();
+        let all = ListEntry {
+            name: "all variants".into(),
+            detail: format!("{} variants", variants.len()),
+            tag: None,
+        };
+        let entries: Vec<ListEntry> = std::iter::once(all)
+            .chain(variants.iter().map(|vn| {
                 let cmd = fossil
                     .resolve_variant(vn, &BTreeMap::new())
-                    .map(|v| v.command)
+                    .map(|v| v.command.to_string())
                     .unwrap_or_default();
                 ListEntry {
                     name: vn.to_string(),
                     detail: cmd,
                     tag: None,
                 }
-            })
+            }))
             .collect();
-        Self {
+        let mut popup = Self {
+            fossil_name: fossil.config.name.clone(),
             fossil_path: fossil.path.clone(),
             project_path,
             variants,
-            selector: SelectorPopup::new("bury variant", entries),
-        }
+            marked: BTreeSet::new(),
+            iterations: String::new(),
+            selector: SelectorPopup::new("", entries),
+        };
+        popup.update_title();
+        popup
     }
 
-    fn start_bury(&mut self) -> BuryAction {
-        let idx = self.selector.list.selected;
-        let variant_name = match self.variants.get(idx) {
-            Some(n) => n.clone(),
-            None => return BuryAction::None,
+    fn update_title(&mut self) {
+        let n = if self.iterations.is_empty() {
+            "default"
+        } else {
+            &self.iterations
         };
+        self.selector.title = format!("bury  n={n}");
+    }
 
-        let project_path = self.project_path.clone();
-        let fossil_path = self.fossil_path.clone();
-        let vname = variant_name.clone();
-
-        let (tx, rx) = mpsc::channel();
-        std::thread::spawn(move || {
-            let result = Project::load(&project_path).and_then(|project| {
-                let fossil = Fossil::load(&fossil_path)?;
-                let v = fossil
-                    .resolve_variant(&vname, &project.config.constants)?;
-                commands::bury(
-                    &fossil,
-                    &project,
-                    None,
-                    Some(v.name),
-                    v.command,
-                    true,
-                )
-            });
-            let _ = tx.send(result.map_err(|e| e.to_string()));
-        });
+    fn toggle_mark(&mut self) {
+        let idx = self.selector.list.selected;
+        let marked = !self.marked.remove(&idx);
+        if marked {
+            self.marked.insert(idx);
+        }
+        if let Some(entry) = self.selector.list.entries.get_mut(idx) {
+            entry.tag = marked.then(|| ("●".to_string(), theme::SELECT));
+        }
+    }
 
-        BuryAction::Started(variant_name.to_string(), rx)
+    /// The marked variants, or the highlighted one when none are.
+    fn queue(&self) -> BuryAction {
+        let picked: Vec<usize> = if self.marked.is_empty() {
+            vec![self.selector.list.selected]
+        } else {
+            self.marked.iter().copied().collect()
+        };
+        let variants = if picked.contains(&ALL) {
+            Vec::new()
+        } else {
+            picked
+                .iter()
+                .filter_map(|&i| self.variants.get(i - 1).cloned())
+                .collect()
+        };
+        BuryAction::Queue(BuryJob {
+            fossil_name: self.fossil_name.clone(),
+            fossil_path: self.fossil_path.clone(),
+            project_path: self.project_path.clone(),
+            variants,
+            iterations: self.iterations.parse().ok().filter(|&n| n > 0),
+        })
     }
 
     pub fn handle_key(&mut self, key: KeyEvent) -> BuryAction {
+        match key.code {
+            KeyCode::Char(' ') => {
+                self.toggle_mark();
+                return BuryAction::None;
+            }
+            KeyCode::Char(c) if c.is_ascii_digit() => {
+                if self.iterations.len() < 6 {
+                    self.iterations.push(c);
+                    self.update_title();
+                }
+                return BuryAction::None;
+            }
+            KeyCode::Backspace => {
+                self.iterations.pop();
+                self.update_title();
+                return BuryAction::None;
+            }
+            _ => {}
+        }
         match self.selector.handle_key(key) {
-            SelectorAction::Select(_) => self.start_bury(),
+            SelectorAction::Select(_) => self.queue(),
             SelectorAction::Dismiss => BuryAction::Dismiss,
             SelectorAction::None => BuryAction::None,
         }
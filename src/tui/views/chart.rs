@@ -0,0 +1,656 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::mpsc;
+
+use crate::analysis::stats::SignificanceTest;
+use crate::analysis::{
+    Metric, Samples, ScriptOptions, Trend, TrendOrder, TrendPoint, stats,
+    topo_position,
+};
+use crate::commands;
+use crate::environment::GitInfo;
+use crate::fossil::Fossil;
+use crate::record::Record;
+use crate::tui::theme;
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::Style;
+use ratatui::symbols::Marker;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{
+    Axis, Bar, BarChart, BarGroup, Chart, Dataset, GraphType, Paragraph,
+};
+
+/// Most bins a histogram is split into.
+const MAX_BINS: usize = 16;
+/// Widest a bar chart label gets before it is cut.
+const MAX_LABEL_W: usize = 16;
+/// Cells kept right of the bars for their values.
+const VALUE_W: usize = 12;
+/// Significance level a trend shift must beat.
+const TREND_ALPHA: f64 = 0.05;
+/// Most shifts listed under the trend chart.
+const MAX_SHIFTS: usize = 4;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ChartKind {
+    Bars,
+    Trend,
+    Histogram,
+}
+
+impl ChartKind {
+    fn name(self) -> &'static str {
+        match self {
+            ChartKind::Bars => "variants",
+            ChartKind::Trend => "trend",
+            ChartKind::Histogram => "iterations",
+        }
+    }
+
+    /// The next chart `v` switches to, `None` going back to text.
+    pub fn next(self) -> Option<Self> {
+        match self {
+            ChartKind::Bars => Some(ChartKind::Trend),
+            ChartKind::Trend => Some(ChartKind::Histogram),
+            ChartKind::Histogram => None,
+        }
+    }
+}
+
+type Loaded = (PathBuf, Result<Samples, String>);
+
+/// What the trend needs to know of a record besides its samples.
+pub struct TrendRecord {
+    dir: PathBuf,
+    id: String,
+    commit: String,
+    sha: String,
+    timestamp: String,
+    repo: Option<PathBuf>,
+}
+
+impl From<&Record> for TrendRecord {
+    fn from(r: &Record) -> Self {
+        Self {
+            dir: r.dir.clone(),
+            id: r.id(),
+            commit: r.manifest.git.commit.clone(),
+            sha: r.manifest.git.sha.clone(),
+            timestamp: r.manifest.timestamp.clone(),
+            repo: r.manifest.git.repo.clone(),
+        }
+    }
+}
+
+/// [Fossil Doc] `ChartPanel`
+/// -------------------------------------------------------------
+/// Charts drawn in the detail pane, so a plot needs no figure script
+/// or display: each variant's mean with a ±1 sd whisker from the last
+/// analysis, one metric across a variant's records with the 95% band
+/// of each mean and the shifts `fossil trend` would report, and the
+/// spread of one record's iterations. Per-record samples come from
+/// the same analysis, or wall time without one, loaded on a
+/// background thread and kept for as long as the panel is open.
+pub struct ChartPanel {
+    pub kind: ChartKind,
+    fossil: Fossil,
+    analysis: Option<String>,
+    /// Samples of each column of the last analysis.
+    columns: Vec<(String, Samples)>,
+    metrics: BTreeSet<String>,
+    metric: Option<String>,
+    /// The current variant's records, oldest first.
+    trend: Vec<TrendRecord>,
+    order: TrendOrder,
+    /// The repo's commits in topological order, listed on first use.
+    topo: Option<Vec<String>>,
+    /// `trend` analyzed, rebuilt on the next tick once stale.
+    analyzed: Option<Trend>,
+    stale: bool,
+    record: Option<PathBuf>,
+    samples: HashMap<PathBuf, Result<Samples, String>>,
+    requested: HashSet<PathBuf>,
+    tx: mpsc::Sender<Loaded>,
+    rx: mpsc::Receiver<Loaded>,
+}
+
+impl ChartPanel {
+    pub fn new(
+        kind: ChartKind,
+        fossil: Fossil,
+        analysis: Option<(&str, &[(String, Metric)])>,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let mut panel = Self {
+            kind,
+            fossil,
+            analysis: None,
+            columns: Vec::new(),
+            metrics: BTreeSet::new(),
+            metric: None,
+            trend: Vec::new(),
+            order: TrendOrder::Time,
+            topo: None,
+            analyzed: None,
+            stale: true,
+            record: None,
+            samples: HashMap::new(),
+            requested: HashSet::new(),
+            tx,
+            rx,
+        };
+        if let Some((name, cols)) = analysis {
+            panel.analysis = Some(name.to_string());
+            panel.columns = cols
+                .iter()
+                .map(|(label, m)| (label.clone(), m.samples()))
+                .collect();
+            for (_, s) in &panel.columns {
+                panel.metrics.extend(s.keys().cloned());
+            }
+        }
+        panel.metric = panel.metrics.first().cloned();
+        panel
+    }
+
+    /// Point the trend at a variant's records (oldest first) and the
+    /// histogram at one record, loading what the current chart needs.
+    pub fn set_target(
+        &mut self,
+        trend: Vec<TrendRecord>,
+        record: Option<PathBuf>,
+    ) {
+        if !trend
+            .iter()
+            .map(|r| &r.dir)
+            .eq(self.trend.iter().map(|r| &r.dir))
+        {
+            self.trend = trend;
+            self.stale = true;
+        }
+        self.record = record;
+        self.load();
+    }
+
+    pub fn set_kind(&mut self, kind: ChartKind) {
+        self.kind = kind;
+        self.load();
+    }
+
+    /// Step through the metrics seen so far.
+    pub fn cycle_metric(&mut self, forward: bool) {
+        let all: Vec<&String> = self.metrics.iter().collect();
+        if all.is_empty() {
+            return;
+        }
+        let pos = self
+            .metric
+            .as_ref()
+            .and_then(|m| all.iter().position(|x| *x == m))
+            .unwrap_or(0);
+        let next = if forward {
+            (pos + 1) % all.len()
+        } else {
+            (pos + all.len() - 1) % all.len()
+        };
+        self.metric = Some(all[next].clone());
+        self.stale = true;
+    }
+
+    /// Line the trend up by commit instead of time, or back.
+    pub fn toggle_order(&mut self) {
+        self.order = match self.order {
+            TrendOrder::Time => TrendOrder::Commit,
+            TrendOrder::Commit => TrendOrder::Time,
+        };
+        self.stale = true;
+    }
+
+    fn load(&mut self) {
+        let wanted: Vec<PathBuf> = match self.kind {
+            ChartKind::Bars => Vec::new(),
+            ChartKind::Trend => {
+                self.trend.iter().map(|r| r.dir.clone()).collect()
+            }
+            ChartKind::Histogram => self.record.iter().cloned().collect(),
+        };
+        let dirs: Vec<PathBuf> = wanted
+            .into_iter()
+            .filter(|d| self.requested.insert(d.clone()))
+            .collect();
+        if dirs.is_empty() {
+            return;
+        }
+        let fossil = self.fossil.clone();
+        let analysis = self.analysis.clone();
+        let tx = self.tx.clone();
+        std::thread::spawn(move || {
+            let options = ScriptOptions::default();
+            let script = match commands::resolve_script(
+                &fossil,
+                analysis.as_deref(),
+                &options,
+            ) {
+                Ok(s) => s,
+                Err(e) => {
+                    for d in dirs {
+                        let _ = tx.send((d, Err(e.to_string())));
+                    }
+                    return;
+                }
+            };
+            for d in dirs {
+                let result =
+                    commands::record_metric(script.as_ref(), &d, &options)
+                        .map(|m| m.samples())
+                        .map_err(|e| e.to_string());
+                if tx.send((d, result)).is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    pub fn tick(&mut self) {
+        while let Ok((dir, result)) = self.rx.try_recv() {
+            if let Ok(s) = &result {
+                self.metrics.extend(s.keys().cloned());
+            }
+            self.samples.insert(dir, result);
+            self.stale = true;
+        }
+        if self.metric.is_none() {
+            self.metric = self.metrics.first().cloned();
+        }
+        if self.stale && self.kind == ChartKind::Trend {
+            self.analyze_trend();
+        }
+    }
+
+    fn analyze_trend(&mut self) {
+        self.stale = false;
+        let Some(metric) = self.metric.clone() else {
+            self.analyzed = None;
+            return;
+        };
+        let mut order: Vec<usize> = (0..self.trend.len()).collect();
+        if self.order == TrendOrder::Commit {
+            let topo = self.topo.get_or_insert_with(|| {
+                let repo = self.trend.iter().find_map(|r| r.repo.clone());
+                GitInfo::topo_order(&repo.unwrap_or(self.fossil.path.clone()))
+            });
+            order.sort_by_key(|&i| {
+                let r = &self.trend[i];
+                topo_position(topo, &r.sha, &r.commit).unwrap_or(usize::MAX)
+            });
+        }
+        let points: Vec<TrendPoint> = order
+            .into_iter()
+            .map(|i| &self.trend[i])
+            .filter_map(|r| match self.samples.get(&r.dir) {
+                Some(Ok(s)) => Some(TrendPoint {
+                    record: r.id.clone(),
+                    commit: r.commit.clone(),
+                    timestamp: r.timestamp.clone(),
+                    samples: s.get(&metric)?.clone(),
+                }),
+                _ => None,
+            })
+            .collect();
+        self.analyzed = Some(Trend::new(
+            metric,
+            points,
+            SignificanceTest::Welch,
+            TREND_ALPHA,
+        ));
+    }
+
+    pub fn title(&self) -> String {
+        let metric = self.metric.as_deref().unwrap_or("-");
+        match self.kind {
+            ChartKind::Trend => {
+                let order = match self.order {
+                    TrendOrder::Time => "by time",
+                    TrendOrder::Commit => "by commit",
+                };
+                format!("trend · {metric} {order}  [ ] o")
+            }
+            kind => format!("{} · {metric}  [ ]", kind.name()),
+        }
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect) {
+        let Some(metric) = self.metric.as_deref() else {
+            let msg = match self.kind {
+                ChartKind::Bars => "run an analysis (a) to chart variants",
+                _ => "loading",
+            };
+            return note(frame, area, msg);
+        };
+        match self.kind {
+            ChartKind::Bars => self.render_bars(frame, area, metric),
+            ChartKind::Trend => self.render_trend(frame, area),
+            ChartKind::Histogram => self.render_histogram(frame, area, metric),
+        }
+    }
+
+    fn render_bars(&self, frame: &mut Frame, area: Rect, metric: &str) {
+        let rows: Vec<(&str, f64, f64)> = self
+            .columns
+            .iter()
+            .filter_map(|(label, s)| {
+                let xs = s.get(metric)?;
+                Some((
+                    label.as_str(),
+                    stats::mean(xs),
+                    stats::variance(xs).sqrt(),
+                ))
+            })
+            .collect();
+        if rows.is_empty() {
+            return note(
+                frame,
+                area,
+                "no variant in the last analysis has this metric",
+            );
+        }
+        let label_w = rows
+            .iter()
+            .map(|(l, ..)| l.chars().count())
+            .max()
+            .unwrap_or(0)
+            .min(MAX_LABEL_W);
+        // Room for the ` ±0.000` after the bar and its whisker.
+        let bar_w = (area.width as usize)
+            .saturating_sub(label_w + 2 + VALUE_W)
+            .max(1);
+        let top = rows
+            .iter()
+            .map(|(_, m, sd)| m + sd)
+            .fold(f64::MIN, f64::max)
+            .max(f64::MIN_POSITIVE);
+        let scale =
+            |x: f64| (x.max(0.0) / top * bar_w as f64).min(bar_w as f64);
+
+        let mut lines = Vec::new();
+        for (label, mean, sd) in rows {
+            let label: String = label.chars().take(label_w).collect();
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("{label:<label_w$}  "),
+                    Style::default().fg(theme::TEXT),
+                ),
+                Span::styled(
+                    bar(scale(mean)),
+                    Style::default().fg(theme::FOCUS),
+                ),
+                Span::styled(
+                    format!(" {mean:.3}"),
+                    Style::default().fg(theme::MUTED),
+                ),
+            ]));
+            lines.push(Line::from(vec![
+                Span::raw(" ".repeat(label_w + 2)),
+                Span::styled(
+                    whisker(scale(mean - sd), scale(mean), scale(mean + sd)),
+                    Style::default().fg(theme::WARN),
+                ),
+                Span::styled(
+                    format!(" ±{sd:.3}"),
+                    Style::default().fg(theme::MUTED),
+                ),
+            ]));
+        }
+        frame.render_widget(Paragraph::new(lines), area);
+    }
+
+    fn render_trend(&self, frame: &mut Frame, area: Rect) {
+        let pending = self
+            .trend
+            .iter()
+            .filter(|r| !self.samples.contains_key(&r.dir))
+            .count();
+        let trend = match &self.analyzed {
+            Some(t) if !t.points.is_empty() => t,
+            _ => {
+                let msg = if pending > 0 || self.stale {
+                    "loading"
+                } else {
+                    "no records have this metric"
+                };
+                return note(frame, area, msg);
+            }
+        };
+        let shifts = trend.changepoints.len().min(MAX_SHIFTS) as u16;
+        let [summary, chart, list] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Min(0),
+            Constraint::Length(shifts.max(1) + 1),
+        ])
+        .areas(area);
+
+        let points = &trend.points;
+        let last = &points[points.len() - 1];
+        let mut text = format!(
+            "{} records  last {:.3} ±{:.3}",
+            points.len(),
+            last.mean(),
+            last.band()
+        );
+        if pending > 0 {
+            text.push_str(&format!("  ({pending} loading)"));
+        }
+        frame.render_widget(
+            Paragraph::new(text).style(Style::default().fg(theme::MUTED)),
+            summary,
+        );
+
+        let at = |i: usize, y: f64| (i as f64, y);
+        let means: Vec<(f64, f64)> = points
+            .iter()
+            .enumerate()
+            .map(|(i, p)| at(i, p.mean()))
+            .collect();
+        let upper: Vec<(f64, f64)> = points
+            .iter()
+            .enumerate()
+            .map(|(i, p)| at(i, p.mean() + p.band()))
+            .collect();
+        let lower: Vec<(f64, f64)> = points
+            .iter()
+            .enumerate()
+            .map(|(i, p)| at(i, p.mean() - p.band()))
+            .collect();
+        let (lo, hi) = lower
+            .iter()
+            .chain(&upper)
+            .fold((f64::MAX, f64::MIN), |(lo, hi), &(_, y)| {
+                (lo.min(y), hi.max(y))
+            });
+        let pad = ((hi - lo) * 0.05).max(f64::EPSILON);
+        let (lo, hi) = (lo - pad, hi + pad);
+        // A shift sits between the last record before it and the first
+        // after, drawn as a rule halfway.
+        let rules: Vec<[(f64, f64); 2]> = trend
+            .changepoints
+            .iter()
+            .map(|c| {
+                let x = c.at as f64 - 0.5;
+                [(x, lo), (x, hi)]
+            })
+            .collect();
+
+        let line = |data, color| {
+            Dataset::default()
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(color))
+                .data(data)
+        };
+        let mut datasets: Vec<Dataset> = rules
+            .iter()
+            .map(|r| line(r.as_slice(), theme::WARN))
+            .collect();
+        datasets.push(line(&upper, theme::MUTED));
+        datasets.push(line(&lower, theme::MUTED));
+        datasets.push(line(&means, theme::FOCUS));
+        datasets.push(
+            Dataset::default()
+                .marker(Marker::Dot)
+                .graph_type(GraphType::Scatter)
+                .style(Style::default().fg(theme::TEXT))
+                .data(&means),
+        );
+
+        let muted = Style::default().fg(theme::MUTED);
+        let x_max = (points.len() - 1).max(1) as f64;
+        frame.render_widget(
+            Chart::new(datasets)
+                .x_axis(
+                    Axis::default()
+                        .style(muted)
+                        .bounds([0.0, x_max])
+                        .labels([
+                            points[0].commit.clone(),
+                            last.commit.clone(),
+                        ]),
+                )
+                .y_axis(Axis::default().style(muted).bounds([lo, hi]).labels(
+                    [
+                        format!("{lo:.2}"),
+                        format!("{:.2}", (lo + hi) / 2.0),
+                        format!("{hi:.2}"),
+                    ],
+                )),
+            chart,
+        );
+
+        let mut lines = vec![Line::default()];
+        if trend.changepoints.is_empty() {
+            lines.push(Line::styled("no significant shifts", muted));
+        }
+        for c in trend.changepoints.iter().take(MAX_SHIFTS) {
+            let first = &points[c.at];
+            let arrow = if c.after > c.before { "▲" } else { "▼" };
+            let pct = c.change().map_or("-".into(), |x| format!("{x:+.1}%"));
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("{arrow} {pct} at {}", first.commit),
+                    Style::default().fg(theme::WARN),
+                ),
+                Span::styled(
+                    format!(
+                        "  {:.3} → {:.3}  p={:.4}",
+                        c.before, c.after, c.p_value
+                    ),
+                    muted,
+                ),
+            ]));
+        }
+        frame.render_widget(Paragraph::new(lines), list);
+    }
+
+    fn render_histogram(&self, frame: &mut Frame, area: Rect, metric: &str) {
+        let xs = match self.record.as_ref().and_then(|r| self.samples.get(r)) {
+            None => return note(frame, area, "loading"),
+            Some(Err(e)) => return note(frame, area, e),
+            Some(Ok(s)) => match s.get(metric) {
+                Some(xs) if !xs.is_empty() => xs,
+                _ => return note(frame, area, "this record lacks the metric"),
+            },
+        };
+        let [summary, chart] =
+            Layout::vertical([Constraint::Length(2), Constraint::Min(0)])
+                .areas(area);
+        frame.render_widget(
+            Paragraph::new(format!(
+                "{} iterations  mean {:.3}  sd {:.3}",
+                xs.len(),
+                stats::mean(xs),
+                stats::variance(xs).sqrt()
+            ))
+            .style(Style::default().fg(theme::MUTED)),
+            summary,
+        );
+
+        let (lo, hi) = xs
+            .iter()
+            .fold((f64::MAX, f64::MIN), |(lo, hi), &x| (lo.min(x), hi.max(x)));
+        let n_bins = ((xs.len() as f64).sqrt().ceil() as usize)
+            .clamp(1, MAX_BINS)
+            .min((chart.width as usize / 2).max(1));
+        let n_bins = if hi > lo { n_bins } else { 1 };
+        let width = (hi - lo) / n_bins as f64;
+        let mut counts = vec![0u64; n_bins];
+        for &x in xs {
+            let i = if width > 0.0 {
+                ((x - lo) / width) as usize
+            } else {
+                0
+            };
+            counts[i.min(n_bins - 1)] += 1;
+        }
+        let bars: Vec<Bar> = counts
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| {
+                Bar::default()
+                    .value(c)
+                    .label(Line::from(format!("{:.1}", lo + width * i as f64)))
+                    .style(Style::default().fg(theme::FOCUS))
+                    .value_style(
+                        Style::default().fg(theme::TEXT).bg(theme::FOCUS),
+                    )
+            })
+            .collect();
+        let gap = 1u16;
+        let bar_width = (chart.width.saturating_sub(gap * (n_bins as u16 - 1))
+            / n_bins as u16)
+            .max(1);
+        frame.render_widget(
+            BarChart::default()
+                .data(BarGroup::default().bars(&bars))
+                .bar_width(bar_width)
+                .bar_gap(gap),
+            chart,
+        );
+    }
+}
+
+fn note(frame: &mut Frame, area: Rect, msg: &str) {
+    frame.render_widget(
+        Paragraph::new(msg.to_string())
+            .style(Style::default().fg(theme::MUTED)),
+        area,
+    );
+}
+
+/// A bar `len` cells long, to an eighth of a cell.
+fn bar(len: f64) -> String {
+    const EIGHTHS: [&str; 8] = ["", "▏", "▎", "▍", "▌", "▋", "▊", "▉"];
+    let full = len.floor() as usize;
+    let part = ((len - full as f64) * 8.0).round() as usize;
+    let mut s = "█".repeat(full);
+    if part == 8 {
+        s.push('█');
+    } else {
+        s.push_str(EIGHTHS[part]);
+    }
+    s
+}
+
+/// `├──┼──┤` spanning cells `lo` to `hi`, crossed at `mid`.
+fn whisker(lo: f64, mid: f64, hi: f64) -> String {
+    let (lo, mid, hi) = (lo as usize, mid as usize, hi as usize);
+    let mut s = " ".repeat(lo);
+    for i in lo..=hi {
+        s.push(match i {
+            _ if i == mid => '┼',
+            _ if i == lo => '├',
+            _ if i == hi => '┤',
+            _ => '─',
+        });
+    }
+    s
+}
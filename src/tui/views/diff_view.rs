@@ -0,0 +1,591 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::mpsc;
+use std::time::Instant;
+
+use crate::analysis::{ScriptOptions, Threshold, stats};
+use crate::commands;
+use crate::fossil::Fossil;
+use crate::record::Record;
+use crate::runner::{Observation, Results};
+use crate::tui::theme;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Borders, Clear, Paragraph};
+
+use super::VimNav;
+use super::main_view::spinner_frame;
+
+/// Unchanged lines shown around each stdout hunk.
+const CONTEXT: usize = 3;
+/// Past this many LCS cells the middle of a stdout diff is shown as
+/// one replaced block instead.
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+pub enum DiffAction {
+    None,
+    Close,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Tab {
+    Manifest,
+    Metrics,
+    Stdout,
+}
+
+const TABS: [(Tab, &str); 3] = [
+    (Tab::Manifest, "manifest"),
+    (Tab::Metrics, "metrics"),
+    (Tab::Stdout, "stdout"),
+];
+
+type MetricRows = Vec<(String, [Option<f64>; 2])>;
+
+/// [Fossil Doc] `DiffView`
+/// -------------------------------------------------------------
+/// Two selected records side by side: their manifests field by
+/// field, the mean of every analysis metric with delta and percent
+/// change, and a unified diff of one iteration's stdout. A metric
+/// with a `[check.thresholds]` entry shows a change in its worse
+/// direction red and one in its better direction green; others,
+/// whose direction fossil can't know, show any change in yellow.
+pub struct DiffView {
+    labels: [String; 2],
+    manifest: Vec<Line<'static>>,
+    thresholds: BTreeMap<String, Threshold>,
+    metrics: Option<Result<Vec<Line<'static>>, String>>,
+    metrics_rx: mpsc::Receiver<Result<MetricRows, String>>,
+    observations: [Vec<Observation>; 2],
+    iteration: usize,
+    stdout: Vec<Line<'static>>,
+    tab: Tab,
+    scroll: usize,
+    start: Instant,
+}
+
+impl DiffView {
+    pub fn new(fossil: Fossil, a: &Record, b: &Record) -> Self {
+        let labels = [record_label(a), record_label(b)];
+        let dirs = [a.dir.clone(), b.dir.clone()];
+        let thresholds = fossil
+            .config
+            .check
+            .as_ref()
+            .map(|c| c.thresholds.clone())
+            .unwrap_or_default();
+
+        let (tx, metrics_rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(metric_rows(&fossil, &dirs));
+        });
+
+        let load = |r: &Record| {
+            Results::load(&r.dir)
+                .map(|res| res.observations)
+                .unwrap_or_default()
+        };
+        let mut view = Self {
+            manifest: manifest_lines(a, b),
+            labels,
+            thresholds,
+            metrics: None,
+            metrics_rx,
+            observations: [load(a), load(b)],
+            iteration: 0,
+            stdout: Vec::new(),
+            tab: Tab::Manifest,
+            scroll: 0,
+            start: Instant::now(),
+        };
+        view.diff_stdout();
+        view
+    }
+
+    pub fn tick(&mut self) {
+        if self.metrics.is_none()
+            && let Ok(result) = self.metrics_rx.try_recv()
+        {
+            self.metrics =
+                Some(result.map(|rows| metric_lines(&rows, &self.thresholds)));
+        }
+    }
+
+    fn lines(&self) -> &[Line<'static>] {
+        match self.tab {
+            Tab::Manifest => &self.manifest,
+            Tab::Metrics => match &self.metrics {
+                Some(Ok(lines)) => lines,
+                _ => &[],
+            },
+            Tab::Stdout => &self.stdout,
+        }
+    }
+
+    fn iterations(&self) -> usize {
+        self.observations[0]
+            .len()
+            .max(self.observations[1].len())
+    }
+
+    fn diff_stdout(&mut self) {
+        let [a, b] = self.observations.each_ref().map(|obs| {
+            obs.get(self.iteration.min(obs.len().saturating_sub(1)))
+        });
+        let (Some(a), Some(b)) = (a, b) else {
+            self.stdout = vec![muted_line("(no observations to compare)")];
+            return;
+        };
+        let mut lines = vec![
+            Line::styled(
+                format!("--- {} #{}", self.labels[0], a.iteration),
+                Style::default().fg(theme::DANGER),
+            ),
+            Line::styled(
+                format!("+++ {} #{}", self.labels[1], b.iteration),
+                Style::default().fg(theme::SELECT),
+            ),
+        ];
+        let hunks = unified(&a.stdout, &b.stdout, CONTEXT);
+        if hunks.is_empty() {
+            lines.push(muted_line("(stdout identical)"));
+        }
+        lines.extend(hunks);
+        self.stdout = lines;
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> DiffAction {
+        let tab = match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => return DiffAction::Close,
+            KeyCode::Char('1') => Some(0),
+            KeyCode::Char('2') => Some(1),
+            KeyCode::Char('3') => Some(2),
+            KeyCode::Tab | KeyCode::Char('l') | KeyCode::Right => TABS
+                .iter()
+                .position(|(t, _)| *t == self.tab)
+                .map(|i| i + 1),
+            KeyCode::BackTab | KeyCode::Char('h') | KeyCode::Left => TABS
+                .iter()
+                .position(|(t, _)| *t == self.tab)
+                .map(|i| i + TABS.len() - 1),
+            KeyCode::Char(']') if self.iteration + 1 < self.iterations() => {
+                self.iteration += 1;
+                self.diff_stdout();
+                None
+            }
+            KeyCode::Char('[') if self.iteration > 0 => {
+                self.iteration -= 1;
+                self.diff_stdout();
+                None
+            }
+            _ => {
+                self.nav(key);
+                None
+            }
+        };
+        if let Some(i) = tab {
+            self.tab = TABS[i % TABS.len()].0;
+            self.scroll = 0;
+        }
+        DiffAction::None
+    }
+
+    pub fn render_popup(&self, frame: &mut Frame, area: Rect) {
+        let width = (area.width * 9 / 10).max(40).min(area.width);
+        let height = (area.height * 9 / 10).max(10).min(area.height);
+        let [popup] = Layout::horizontal([Constraint::Length(width)])
+            .flex(Flex::Center)
+            .areas(
+                Layout::vertical([Constraint::Length(height)])
+                    .flex(Flex::Center)
+                    .areas::<1>(area)[0],
+            );
+
+        frame.render_widget(Clear, popup);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(theme::FOCUS))
+            .title(Line::from(vec![
+                Span::styled(" a ", Style::default().fg(theme::DANGER)),
+                Span::styled(
+                    format!("{}  ", self.labels[0]),
+                    Style::default().fg(theme::TEXT),
+                ),
+                Span::styled("b ", Style::default().fg(theme::SELECT)),
+                Span::styled(
+                    format!("{} ", self.labels[1]),
+                    Style::default().fg(theme::TEXT),
+                ),
+            ]));
+        let inner = block.inner(popup);
+        frame.render_widget(block, popup);
+
+        let [tabs, body] =
+            Layout::vertical([Constraint::Length(2), Constraint::Min(0)])
+                .areas(inner);
+
+        let mut bar: Vec<Span> = Vec::new();
+        for (i, (tab, name)) in TABS.iter().enumerate() {
+            let style = if *tab == self.tab {
+                Style::default()
+                    .fg(theme::FOCUS)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(theme::MUTED)
+            };
+            bar.push(Span::styled(format!(" {} {name} ", i + 1), style));
+        }
+        if self.tab == Tab::Stdout && self.iterations() > 0 {
+            bar.push(Span::styled(
+                format!(
+                    "  iteration {}/{}  [ ]",
+                    self.iteration + 1,
+                    self.iterations()
+                ),
+                Style::default().fg(theme::MUTED),
+            ));
+        }
+        frame.render_widget(Paragraph::new(Line::from(bar)), tabs);
+
+        let lines: Vec<Line> = match (&self.tab, &self.metrics) {
+            (Tab::Metrics, None) => vec![muted_line(&format!(
+                "running analysis {}",
+                spinner_frame(self.start)
+            ))],
+            (Tab::Metrics, Some(Err(msg))) => vec![Line::styled(
+                msg.clone(),
+                Style::default().fg(theme::DANGER),
+            )],
+            _ => self
+                .lines()
+                .iter()
+                .skip(self.scroll)
+                .cloned()
+                .collect(),
+        };
+        frame.render_widget(Paragraph::new(lines), body);
+    }
+}
+
+impl VimNav for DiffView {
+    fn pos(&self) -> usize {
+        self.scroll
+    }
+    fn set_pos(&mut self, pos: usize) {
+        self.scroll = pos;
+    }
+    fn max_pos(&self) -> usize {
+        self.lines().len().saturating_sub(1)
+    }
+}
+
+fn record_label(record: &Record) -> String {
+    let m = &record.manifest;
+    let variant = m.variant.as_ref().map_or("untagged", |v| v.as_str());
+    let ts = m.timestamp.get(5..16).unwrap_or(&m.timestamp);
+    format!("{variant} @ {}", ts.replace('T', " "))
+}
+
+fn muted_line(text: &str) -> Line<'static> {
+    Line::styled(text.to_string(), Style::default().fg(theme::MUTED))
+}
+
+/// The manifests' summary lines and env vars, one row per field,
+/// rows that differ in full color and the rest muted.
+fn manifest_lines(a: &Record, b: &Record) -> Vec<Line<'static>> {
+    let (sa, sb) = (a.manifest.summary(), b.manifest.summary());
+    let mut labels: Vec<&str> = sa.iter().map(|(l, _)| *l).collect();
+    for (l, _) in &sb {
+        if !labels.contains(l) {
+            labels.push(l);
+        }
+    }
+    let get = |s: &[(&str, String)], label: &str| {
+        s.iter()
+            .find(|(l, _)| *l == label)
+            .map_or("-".to_string(), |(_, v)| v.clone())
+    };
+    let mut rows: Vec<(String, String, String)> = labels
+        .into_iter()
+        .map(|l| (l.to_string(), get(&sa, l), get(&sb, l)))
+        .collect();
+
+    let empty = BTreeMap::new();
+    let vars = |r: &Record| {
+        r.manifest
+            .env
+            .as_ref()
+            .map_or(&empty, |e| &e.vars)
+            .clone()
+    };
+    let (va, vb) = (vars(a), vars(b));
+    let keys: BTreeSet<&String> = va.keys().chain(vb.keys()).collect();
+    for key in keys {
+        let (x, y) = (va.get(key), vb.get(key));
+        if x != y {
+            let unset = || "(unset)".to_string();
+            rows.push((
+                format!("${key}"),
+                x.cloned().unwrap_or_else(unset),
+                y.cloned().unwrap_or_else(unset),
+            ));
+        }
+    }
+
+    let w = rows
+        .iter()
+        .map(|(_, x, _)| x.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(60);
+    rows.into_iter()
+        .map(|(label, x, y)| {
+            let same = x == y;
+            let label_style = Style::default().fg(theme::MUTED);
+            let (xs, ys) = if same {
+                (label_style, label_style)
+            } else {
+                (
+                    Style::default().fg(theme::DANGER),
+                    Style::default().fg(theme::SELECT),
+                )
+            };
+            let marker = if same { "  " } else { "≠ " };
+            Line::from(vec![
+                Span::styled(
+                    format!("{marker}{label:<13}"),
+                    if same {
+                        label_style
+                    } else {
+                        Style::default().fg(theme::WARN)
+                    },
+                ),
+                Span::styled(format!("{x:<w$}  "), xs),
+                Span::styled(y, ys),
+            ])
+        })
+        .collect()
+}
+
+/// Mean of every numeric metric of both records, in key order.
+fn metric_rows(
+    fossil: &Fossil,
+    dirs: &[std::path::PathBuf; 2],
+) -> Result<MetricRows, String> {
+    let options = ScriptOptions::default();
+    let script = commands::resolve_script(fossil, None, &options)
+        .map_err(|e| e.to_string())?;
+    let samples = dirs
+        .iter()
+        .map(|d| {
+            commands::record_metric(script.as_ref(), d, &options)
+                .map(|m| m.samples())
+                .map_err(|e| e.to_string())
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let keys: BTreeSet<&String> =
+        samples.iter().flat_map(|s| s.keys()).collect();
+    Ok(keys
+        .into_iter()
+        .map(|k| {
+            let mean = |i: usize| samples[i].get(k).map(|xs| stats::mean(xs));
+            (k.clone(), [mean(0), mean(1)])
+        })
+        .collect())
+}
+
+/// One row per metric: both means, the delta and percent change,
+/// colored by the metric's threshold direction where it has one.
+fn metric_lines(
+    rows: &MetricRows,
+    thresholds: &BTreeMap<String, Threshold>,
+) -> Vec<Line<'static>> {
+    if rows.is_empty() {
+        return vec![muted_line("(no numeric metrics)")];
+    }
+    let w = rows
+        .iter()
+        .map(|(m, _)| m.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(6);
+    let header = format!(
+        "{:<w$}  {:>14}  {:>14}  {:>12}  {:>9}",
+        "metric", "a", "b", "delta", "change"
+    );
+    let mut lines = vec![Line::styled(
+        header,
+        Style::default()
+            .fg(theme::MUTED)
+            .add_modifier(Modifier::BOLD),
+    )];
+    let fmt = |x: Option<f64>| x.map_or("-".into(), |x| format!("{x:.3}"));
+    for (metric, [a, b]) in rows {
+        let (delta, change, color) = match (a, b) {
+            (Some(a), Some(b)) => {
+                let d = b - a;
+                let pct = if *a == 0.0 {
+                    "-".to_string()
+                } else {
+                    format!("{:+.2}%", d / a.abs() * 100.0)
+                };
+                let color = match thresholds.get(metric) {
+                    _ if d == 0.0 => theme::MUTED,
+                    Some(t) if (d > 0.0) == t.higher_is_worse => theme::DANGER,
+                    Some(_) => theme::SELECT,
+                    None => theme::WARN,
+                };
+                (format!("{d:+.3}"), pct, color)
+            }
+            _ => ("-".into(), "-".into(), theme::WARN),
+        };
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{metric:<w$}  {:>14}  {:>14}  ", fmt(*a), fmt(*b)),
+                Style::default().fg(theme::TEXT),
+            ),
+            Span::styled(
+                format!("{delta:>12}  {change:>9}"),
+                Style::default().fg(color),
+            ),
+        ]));
+    }
+    lines
+}
+
+#[derive(Clone, Copy)]
+enum Edit {
+    /// Index into `a`.
+    Same(usize),
+    Del(usize),
+    Add(usize),
+}
+
+/// Line edits turning `a` into `b`: a longest common subsequence over
+/// whatever lies between their common prefix and suffix.
+fn edits(a: &[String], b: &[String]) -> Vec<Edit> {
+    let pre = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suf = a[pre..]
+        .iter()
+        .rev()
+        .zip(b[pre..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (ma, mb) = (&a[pre..a.len() - suf], &b[pre..b.len() - suf]);
+
+    let mut out: Vec<Edit> = (0..pre).map(Edit::Same).collect();
+    if ma.len() * mb.len() > MAX_LCS_CELLS {
+        out.extend((0..ma.len()).map(|i| Edit::Del(pre + i)));
+        out.extend((0..mb.len()).map(|j| Edit::Add(pre + j)));
+    } else {
+        // lcs[i][j]: length of the LCS of ma[i..] and mb[j..].
+        let cols = mb.len() + 1;
+        let mut lcs = vec![0u32; (ma.len() + 1) * cols];
+        for i in (0..ma.len()).rev() {
+            for j in (0..mb.len()).rev() {
+                lcs[i * cols + j] = if ma[i] == mb[j] {
+                    lcs[(i + 1) * cols + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * cols + j].max(lcs[i * cols + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < ma.len() || j < mb.len() {
+            if i < ma.len() && j < mb.len() && ma[i] == mb[j] {
+                out.push(Edit::Same(pre + i));
+                i += 1;
+                j += 1;
+            } else if i < ma.len()
+                && (j == mb.len()
+                    || lcs[(i + 1) * cols + j] >= lcs[i * cols + j + 1])
+            {
+                out.push(Edit::Del(pre + i));
+                i += 1;
+            } else {
+                out.push(Edit::Add(pre + j));
+                j += 1;
+            }
+        }
+    }
+    let sa = a.len() - suf;
+    out.extend((0..suf).map(|k| Edit::Same(sa + k)));
+    out
+}
+
+/// `a` against `b` as unified diff hunks with `context` lines around
+/// each change; empty when they are equal.
+fn unified(a: &[String], b: &[String], context: usize) -> Vec<Line<'static>> {
+    let edits = edits(a, b);
+    let changed: Vec<usize> = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| !matches!(e, Edit::Same(_)))
+        .map(|(i, _)| i)
+        .collect();
+
+    // Group changes whose unchanged gap is short enough to share
+    // context into one hunk of edit indices.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &c in &changed {
+        let start = c.saturating_sub(context);
+        let end = (c + context + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some((_, e)) if start <= *e => *e = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    // Where each edit starts in `a` and in `b`.
+    let mut pos = Vec::with_capacity(edits.len());
+    let (mut pa, mut pb) = (0, 0);
+    for e in &edits {
+        pos.push((pa, pb));
+        match e {
+            Edit::Same(_) => {
+                pa += 1;
+                pb += 1;
+            }
+            Edit::Del(_) => pa += 1,
+            Edit::Add(_) => pb += 1,
+        }
+    }
+
+    let mut lines = Vec::new();
+    for (start, end) in hunks {
+        let span = &edits[start..end];
+        let na = span
+            .iter()
+            .filter(|e| !matches!(e, Edit::Add(_)))
+            .count();
+        let nb = span
+            .iter()
+            .filter(|e| !matches!(e, Edit::Del(_)))
+            .count();
+        let (pa, pb) = pos[start];
+        lines.push(Line::styled(
+            format!("@@ -{},{na} +{},{nb} @@", pa + 1, pb + 1),
+            Style::default().fg(theme::FOCUS),
+        ));
+        for e in span {
+            lines.push(match *e {
+                Edit::Same(i) => Line::styled(
+                    format!(" {}", a[i]),
+                    Style::default().fg(theme::MUTED),
+                ),
+                Edit::Del(i) => Line::styled(
+                    format!("-{}", a[i]),
+                    Style::default().fg(theme::DANGER),
+                ),
+                Edit::Add(j) => Line::styled(
+                    format!("+{}", b[j]),
+                    Style::default().fg(theme::SELECT),
+                ),
+            });
+        }
+    }
+    lines
+}
@@ -0,0 +1,194 @@
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Span;
+
+use crate::record::Record;
+use crate::tui::theme;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Field {
+    Variant,
+    Commit,
+    Branch,
+    Tag,
+    Command,
+    Timestamp,
+}
+
+const FIELDS: &[Field] = &[
+    Field::Variant,
+    Field::Commit,
+    Field::Branch,
+    Field::Tag,
+    Field::Command,
+    Field::Timestamp,
+];
+
+enum Term {
+    /// Fuzzy match against one field, or any when `None`.
+    Fuzzy(Option<Field>, String),
+    /// Plain substring of the command line.
+    Command(String),
+    Since(String),
+    Until(String),
+}
+
+/// [Fossil Doc] `RecordFilter`
+/// -------------------------------------------------------------
+/// The `/` query narrowing the record grid. Terms are separated by
+/// whitespace and all must match. A bare term fuzzy-matches any
+/// field; `v:`, `c:`, `b:` and `t:` restrict it to the variant,
+/// commit, branch or tags, and `cmd:` looks for a substring of the
+/// command. `since:` and `until:` bound the timestamp by prefix, so
+/// `since:2026-10 until:2026-10-18` spans whole days.
+#[derive(Default)]
+pub struct RecordFilter {
+    pub query: String,
+    terms: Vec<Term>,
+}
+
+impl RecordFilter {
+    pub fn new(query: &str) -> Self {
+        let terms = query
+            .split_whitespace()
+            .filter_map(|word| {
+                let (key, value) = match word.split_once(':') {
+                    Some((k, v)) => (Some(k), v),
+                    None => (None, word),
+                };
+                if value.is_empty() {
+                    return None;
+                }
+                let value = value.to_lowercase();
+                let field = match key {
+                    None => None,
+                    Some("v" | "variant") => Some(Field::Variant),
+                    Some("c" | "commit") => Some(Field::Commit),
+                    Some("b" | "branch") => Some(Field::Branch),
+                    Some("t" | "tag") => Some(Field::Tag),
+                    Some("cmd" | "command") => {
+                        return Some(Term::Command(value));
+                    }
+                    Some("since") => return Some(Term::Since(value)),
+                    Some("until") => return Some(Term::Until(value)),
+                    // Not a field after all, e.g. `O2:native`.
+                    Some(_) => {
+                        return Some(Term::Fuzzy(None, word.to_lowercase()));
+                    }
+                };
+                Some(Term::Fuzzy(field, value))
+            })
+            .collect();
+        Self {
+            query: query.to_string(),
+            terms,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.terms.is_empty()
+    }
+
+    pub fn matches(&self, record: &Record) -> bool {
+        let ts = record.manifest.timestamp.to_lowercase();
+        self.terms.iter().all(|term| match term {
+            Term::Fuzzy(field, q) => {
+                let fields =
+                    field.as_ref().map_or(FIELDS, std::slice::from_ref);
+                fields.iter().any(|&f| {
+                    values(record, f).iter().any(|v| fuzzy(q, v).is_some())
+                })
+            }
+            Term::Command(q) => record
+                .manifest
+                .command
+                .to_string()
+                .to_lowercase()
+                .contains(q.as_str()),
+            Term::Since(bound) => ts.as_str() >= bound.as_str(),
+            Term::Until(bound) => {
+                ts.get(..bound.len()).unwrap_or(&ts) <= bound.as_str()
+            }
+        })
+    }
+
+    /// Positions of the characters of `text`, shown for `field`, that
+    /// some term matched.
+    pub fn hits(&self, field: Field, text: &str) -> Vec<usize> {
+        let mut hits: Vec<usize> = self
+            .terms
+            .iter()
+            .filter_map(|term| match term {
+                Term::Fuzzy(f, q) if f.is_none_or(|f| f == field) => {
+                    fuzzy(q, text)
+                }
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        hits.sort_unstable();
+        hits.dedup();
+        hits
+    }
+
+    /// `text` as spans in `style`, with the characters matched for
+    /// `field` picked out.
+    pub fn highlight(
+        &self,
+        field: Field,
+        text: &str,
+        style: Style,
+    ) -> Vec<Span<'static>> {
+        let hits = self.hits(field, text);
+        if hits.is_empty() {
+            return vec![Span::styled(text.to_string(), style)];
+        }
+        let hit_style = style.fg(theme::WARN).add_modifier(Modifier::BOLD);
+        let mut spans: Vec<Span> = Vec::new();
+        let mut run = String::new();
+        let mut in_hit = false;
+        for (i, c) in text.chars().enumerate() {
+            let hit = hits.binary_search(&i).is_ok();
+            if hit != in_hit && !run.is_empty() {
+                let s = if in_hit { hit_style } else { style };
+                spans.push(Span::styled(std::mem::take(&mut run), s));
+            }
+            in_hit = hit;
+            run.push(c);
+        }
+        let s = if in_hit { hit_style } else { style };
+        spans.push(Span::styled(run, s));
+        spans
+    }
+}
+
+fn values(record: &Record, field: Field) -> Vec<String> {
+    let m = &record.manifest;
+    match field {
+        Field::Variant => vec![
+            m.variant
+                .as_ref()
+                .map_or("untagged", |v| v.as_str())
+                .to_string(),
+        ],
+        Field::Commit => vec![m.git.commit.clone(), m.git.sha.clone()],
+        Field::Branch => vec![m.git.branch.clone()],
+        Field::Tag => m.tags.clone(),
+        Field::Command => vec![m.command.to_string()],
+        Field::Timestamp => vec![m.timestamp.clone()],
+    }
+}
+
+/// Case-insensitive subsequence match of `query` (already lowercase)
+/// in `text`, returning the character positions it used.
+fn fuzzy(query: &str, text: &str) -> Option<Vec<usize>> {
+    let mut want = query.chars().peekable();
+    let mut hits = Vec::new();
+    for (i, c) in text.chars().enumerate() {
+        let Some(&w) = want.peek() else { break };
+        if c.to_lowercase().eq(std::iter::once(w)) {
+            hits.push(i);
+            want.next();
+        }
+    }
+    want.peek().is_none().then_some(hits)
+}
@@ -18,9 +18,14 @@ pub struct VariantGrid {
 }
 
 impl VariantGrid {
-    pub fn from_records(records: &[Record]) -> Self {
+    /// Group the records `keep` lets through into a column per
+    /// variant.
+    pub fn from_records(
+        records: &[Record],
+        keep: impl Fn(&Record) -> bool,
+    ) -> Self {
         let mut groups: BTreeMap<String, Vec<usize>> = BTreeMap::new();
-        for (i, r) in records.iter().enumerate() {
+        for (i, r) in records.iter().enumerate().filter(|(_, r)| keep(r)) {
             let v = r
                 .manifest
                 .variant
@@ -47,6 +52,37 @@ impl VariantGrid {
         }
     }
 
+    /// Every record in the grid, column by column.
+    pub fn record_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.columns
+            .iter()
+            .flat_map(|c| c.record_indices.iter().copied())
+    }
+
+    /// Move to `record`, if the grid holds it, otherwise stay in the
+    /// column named `col` as near the old grid's `row` as it allows.
+    pub fn focus(
+        &mut self,
+        col: Option<&str>,
+        record: Option<usize>,
+        row: usize,
+    ) {
+        let Some(ci) = self.columns.iter().position(|c| Some(&*c.name) == col)
+        else {
+            return;
+        };
+        let rows = &self.columns[ci].record_indices;
+        self.col = ci;
+        self.row = record
+            .and_then(|r| rows.iter().position(|&i| i == r))
+            .unwrap_or(row)
+            .min(rows.len().saturating_sub(1));
+    }
+
+    pub fn current_column_name(&self) -> Option<&str> {
+        self.columns.get(self.col).map(|c| c.name.as_str())
+    }
+
     pub fn current_record_idx(&self) -> Option<usize> {
         self.columns
             .get(self.col)
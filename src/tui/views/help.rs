@@ -26,7 +26,8 @@ const SECTIONS: &[(&str, &[(&str, &str)])] = &[
         "Selection",
         &[
             ("Space", "toggle select record"),
-            ("Esc", "clear selection"),
+            ("Esc", "clear selection, then filter"),
+            ("/", "filter records, e.g. t:nightly"),
         ],
     ),
     (
@@ -36,7 +37,13 @@ const SECTIONS: &[(&str, &[(&str, &str)])] = &[
             ("f", "switch fossil"),
             ("e", "edit config / scripts"),
             ("a", "run analysis"),
-            ("b", "bury variant"),
+            ("c", "compare two selected records"),
+            ("v", "cycle charts in the preview"),
+            ("[ / ]", "chart metric (in preview)"),
+            ("o", "trend by time or commit"),
+            ("b", "bury (space marks, digits set n)"),
+            ("r", "show run panel / queue"),
+            ("x / X", "cancel run / run and queue"),
             ("d", "delete record"),
             ("q", "quit"),
             ("Ctrl-c", "force quit"),
@@ -57,7 +64,7 @@ impl HelpOverlay {
 
     pub fn render(frame: &mut Frame, area: Rect) {
         let width = 50u16.min(area.width.saturating_sub(4));
-        let height = 28u16.min(area.height.saturating_sub(4));
+        let height = 38u16.min(area.height.saturating_sub(4));
 
         let [popup_area] = Layout::horizontal([Constraint::Length(width)])
             .flex(Flex::Center)
@@ -1,10 +1,10 @@
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::path::PathBuf;
 use std::sync::mpsc;
 use std::time::Instant;
 
 use crate::tui::theme;
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::Frame;
 use ratatui::layout::{Constraint, Flex, Layout, Rect};
 use ratatui::style::{Color, Modifier, Style};
@@ -20,7 +20,11 @@ use crate::record::Record;
 
 use super::analysis_popup::{AnalysisAction, AnalysisPopupState};
 use super::bury_popup::{BuryAction, BuryPopupState};
+use super::chart::{ChartKind, ChartPanel, TrendRecord};
+use super::diff_view::{DiffAction, DiffView};
+use super::filter::{Field, RecordFilter};
 use super::grid::VariantGrid;
+use super::run_panel::{BuryJob, RunPanel, RunPanelAction};
 use super::{
     AppAction, ListEntry, PreviewPanel, SelectorAction, SelectorPopup,
 };
@@ -76,12 +80,6 @@ enum Focus {
     Detail,
 }
 
-struct BgBury {
-    variant: String,
-    rx: mpsc::Receiver<Result<String, String>>,
-    start: Instant,
-}
-
 struct FigureLoading {
     name: String,
     rx: mpsc::Receiver<Result<String, String>>,
@@ -95,6 +93,10 @@ enum Mode {
     EditSelector(SelectorPopup, Vec<PathBuf>),
     AnalysisPopup(Box<AnalysisPopupState>),
     BuryPopup(BuryPopupState),
+    RunPanel,
+    Diff(Box<DiffView>),
+    /// Typing a `/` query; holds the one to restore on esc.
+    Filter(String),
     FigureSelector(SelectorPopup, Vec<String>),
     FigureRunning(FigureLoading),
     DeleteConfirm(usize),
@@ -109,13 +111,17 @@ pub struct MainView {
     fossil_idx: usize,
     records: Vec<Record>,
     grid: VariantGrid,
+    filter: RecordFilter,
     selected: BTreeSet<usize>,
     preview: Option<PreviewPanel>,
     preview_index: Option<usize>,
     last_analysis: Option<Vec<(String, crate::analysis::Metric)>>,
+    last_analysis_name: Option<String>,
+    chart: Option<ChartPanel>,
     focus: Focus,
     mode: Mode,
-    bg_bury: Option<BgBury>,
+    bg_bury: Option<RunPanel>,
+    bury_queue: VecDeque<BuryJob>,
 }
 
 impl MainView {
@@ -124,7 +130,7 @@ impl MainView {
         fossils: Vec<Fossil>,
         records: Vec<Record>,
     ) -> Self {
-        let grid = VariantGrid::from_records(&records);
+        let grid = VariantGrid::from_records(&records, |_| true);
         let initial_idx = grid.current_record_idx();
         let preview = initial_idx
             .and_then(|i| records.get(i))
@@ -135,6 +141,7 @@ impl MainView {
             fossil_idx: 0,
             fossils,
             grid,
+            filter: RecordFilter::default(),
             selected: BTreeSet::new(),
             preview,
             preview_index: initial_idx,
@@ -142,7 +149,10 @@ impl MainView {
             focus: Focus::Master,
             mode: Mode::Browse,
             last_analysis: None,
+            last_analysis_name: None,
+            chart: None,
             bg_bury: None,
+            bury_queue: VecDeque::new(),
         }
     }
 
@@ -175,9 +185,12 @@ impl MainView {
     }
 
     pub fn bg_bury_label(&self) -> Option<String> {
-        self.bg_bury.as_ref().map(|b| {
-            format!("burying {} {}", b.variant, spinner_frame(b.start))
-        })
+        self.bg_bury
+            .as_ref()
+            .map(|panel| match self.bury_queue.len() {
+                0 => panel.label(),
+                n => format!("{}  +{n} queued", panel.label()),
+            })
     }
 
     pub fn fossil_name(&self) -> &str {
@@ -194,17 +207,31 @@ impl MainView {
             | Mode::EditSelector(..) => {
                 vec![("enter", "select"), ("esc", "close")]
             }
-            Mode::AnalysisPopup(_)
-            | Mode::BuryPopup(_)
-            | Mode::FigureRunning(_) => {
+            Mode::AnalysisPopup(_) | Mode::FigureRunning(_) => {
                 vec![("enter", "run"), ("esc", "close")]
             }
+            Mode::BuryPopup(_) => vec![
+                ("space", "mark"),
+                ("0-9", "iterations"),
+                ("enter", "queue"),
+                ("esc", "close"),
+            ],
             Mode::FigureSelector(..) => {
                 vec![("enter", "select"), ("esc", "close")]
             }
             Mode::DeleteConfirm(_) => {
                 vec![("y", "confirm delete"), ("n/esc", "cancel")]
             }
+            Mode::Filter(_) => vec![("enter", "apply"), ("esc", "cancel")],
+            Mode::Diff(_) => vec![
+                ("tab", "section"),
+                ("[ ]", "iteration"),
+                ("j/k", "scroll"),
+                ("esc", "close"),
+            ],
+            Mode::RunPanel => {
+                vec![("x", "cancel run"), ("X", "cancel all"), ("esc", "hide")]
+            }
             Mode::Browse => match self.focus {
                 Focus::Master => {
                     let mut h = vec![
@@ -217,9 +244,13 @@ impl MainView {
                         ("d", "delete"),
                         ("?", "help"),
                     ];
-                    if !self.selected.is_empty() {
+                    if !self.selected.is_empty() || self.filter.is_active() {
                         h.insert(2, ("esc", "clear"));
                     }
+                    h.insert(2, ("/", "filter"));
+                    if self.selected.len() == 2 {
+                        h.insert(3, ("c", "compare"));
+                    }
                     h
                 }
                 Focus::Detail => {
@@ -227,8 +258,15 @@ impl MainView {
                         ("j/k", "scroll"),
                         ("h/l", "pan"),
                         ("c", "copy"),
+                        ("v", "chart"),
                         ("tab", "list"),
                     ];
+                    if let Some(ref chart) = self.chart {
+                        h.insert(4, ("[ ]", "metric"));
+                        if chart.kind == ChartKind::Trend {
+                            h.insert(5, ("o", "order"));
+                        }
+                    }
                     if self.last_analysis.is_some() {
                         h.push(("f", "figure"));
                     }
@@ -242,12 +280,7 @@ impl MainView {
         if let Mode::AnalysisPopup(ref mut popup) = self.mode {
             match popup.tick() {
                 AnalysisAction::Output(name, output, cols) => {
-                    if let Some(ref mut p) = self.preview {
-                        p.set_content(&format!("analysis: {name}"), &output);
-                    }
-                    self.last_analysis = Some(cols);
-                    self.mode = Mode::Browse;
-                    self.focus = Focus::Detail;
+                    self.show_analysis(name, &output, cols);
                 }
                 AnalysisAction::Flash(msg) => {
                     self.mode = Mode::Browse;
@@ -256,23 +289,24 @@ impl MainView {
                 _ => {}
             }
         }
-        if let Some(ref bg) = self.bg_bury {
-            match bg.rx.try_recv() {
-                Ok(Ok(summary)) => {
-                    self.bg_bury = None;
+        if let Mode::Diff(ref mut diff) = self.mode {
+            diff.tick();
+        }
+        if let Some(ref mut chart) = self.chart {
+            chart.tick();
+        }
+        if let Some(result) = self.bg_bury.as_mut().and_then(RunPanel::tick) {
+            self.bg_bury = self.bury_queue.pop_front().map(RunPanel::spawn);
+            if self.bg_bury.is_none() && matches!(self.mode, Mode::RunPanel) {
+                self.mode = Mode::Browse;
+            }
+            return match result {
+                Ok(summary) => {
                     self.reload_records();
-                    return AppAction::Flash(summary);
+                    AppAction::Flash(summary)
                 }
-                Ok(Err(msg)) => {
-                    self.bg_bury = None;
-                    return AppAction::Flash(msg);
-                }
-                Err(mpsc::TryRecvError::Disconnected) => {
-                    self.bg_bury = None;
-                    return AppAction::Flash("bury thread panicked".into());
-                }
-                _ => {}
-            }
+                Err(msg) => AppAction::Flash(msg),
+            };
         }
         if let Mode::FigureRunning(ref loading) = self.mode {
             match loading.rx.try_recv() {
@@ -307,6 +341,7 @@ impl MainView {
                 Vec<(String, crate::analysis::Metric)>,
             ),
             RunFigure(usize),
+            ShowRunPanel,
             Flash(String),
             Browse,
         }
@@ -346,16 +381,62 @@ impl MainView {
             Mode::FigureRunning(_) => Resolved::None,
             Mode::BuryPopup(popup) => match popup.handle_key(key) {
                 BuryAction::Dismiss => Resolved::Dismiss,
-                BuryAction::Started(variant, rx) => {
-                    self.bg_bury = Some(BgBury {
-                        variant,
-                        rx,
-                        start: Instant::now(),
-                    });
-                    Resolved::Dismiss
+                BuryAction::Queue(job) => {
+                    if self.bg_bury.is_none() {
+                        self.bg_bury = Some(RunPanel::spawn(job));
+                        Resolved::ShowRunPanel
+                    } else {
+                        let msg = format!("queued {}", job.label());
+                        self.bury_queue.push_back(job);
+                        Resolved::Flash(msg)
+                    }
                 }
                 BuryAction::None => Resolved::None,
             },
+            Mode::RunPanel => match self.bg_bury.as_mut() {
+                Some(panel) => match panel.handle_key(key) {
+                    RunPanelAction::Hide => Resolved::Dismiss,
+                    RunPanelAction::CancelAll => {
+                        self.bury_queue.clear();
+                        Resolved::None
+                    }
+                    RunPanelAction::None => Resolved::None,
+                },
+                None => Resolved::Dismiss,
+            },
+            Mode::Diff(diff) => match diff.handle_key(key) {
+                DiffAction::Close => Resolved::Dismiss,
+                DiffAction::None => Resolved::None,
+            },
+            Mode::Filter(previous) => {
+                let mut query = self.filter.query.clone();
+                match key.code {
+                    KeyCode::Enter => {
+                        self.mode = Mode::Browse;
+                        return AppAction::None;
+                    }
+                    KeyCode::Esc => query = std::mem::take(previous),
+                    KeyCode::Backspace => {
+                        query.pop();
+                    }
+                    KeyCode::Char('u')
+                        if key.modifiers.contains(KeyModifiers::CONTROL) =>
+                    {
+                        query.clear();
+                    }
+                    KeyCode::Char(c)
+                        if !key.modifiers.contains(KeyModifiers::CONTROL) =>
+                    {
+                        query.push(c);
+                    }
+                    _ => return AppAction::None,
+                }
+                self.set_filter(&query);
+                if key.code == KeyCode::Esc {
+                    self.mode = Mode::Browse;
+                }
+                return AppAction::None;
+            }
             Mode::DeleteConfirm(idx) => {
                 let idx = *idx;
                 match key.code {
@@ -394,18 +475,17 @@ impl MainView {
                 return AppAction::Edit(path);
             }
             Resolved::AnalysisOutput(name, output, cols) => {
-                if let Some(ref mut p) = self.preview {
-                    p.set_content(&format!("analysis: {name}"), &output);
-                }
-                self.last_analysis = Some(cols);
-                self.mode = Mode::Browse;
-                self.focus = Focus::Detail;
+                self.show_analysis(name, &output, cols);
                 return AppAction::None;
             }
             Resolved::RunFigure(i) => {
                 self.start_figure(i);
                 return AppAction::None;
             }
+            Resolved::ShowRunPanel => {
+                self.mode = Mode::RunPanel;
+                return AppAction::None;
+            }
             Resolved::Flash(msg) => {
                 self.mode = Mode::Browse;
                 return AppAction::Flash(msg);
@@ -413,12 +493,30 @@ impl MainView {
             Resolved::Browse => {}
         }
 
+        if key.code == KeyCode::Char('v') {
+            self.cycle_chart();
+            return AppAction::None;
+        }
+
         match self.focus {
             Focus::Detail => {
                 if matches!(key.code, KeyCode::Tab | KeyCode::Esc) {
                     self.focus = Focus::Master;
                     return AppAction::None;
                 }
+                if let (Some(chart), KeyCode::Char(c @ ('[' | ']'))) =
+                    (self.chart.as_mut(), key.code)
+                {
+                    chart.cycle_metric(c == ']');
+                    return AppAction::None;
+                }
+                if let (Some(chart), KeyCode::Char('o')) =
+                    (self.chart.as_mut(), key.code)
+                    && chart.kind == ChartKind::Trend
+                {
+                    chart.toggle_order();
+                    return AppAction::None;
+                }
                 if key.code == KeyCode::Char('f')
                     && self.last_analysis.is_some()
                 {
@@ -459,9 +557,20 @@ impl MainView {
                         AppAction::None
                     }
                     KeyCode::Esc => {
+                        if self.selected.is_empty() {
+                            self.set_filter("");
+                        }
                         self.selected.clear();
                         AppAction::None
                     }
+                    KeyCode::Char('c') => match self.open_diff() {
+                        Some(msg) => AppAction::Flash(msg),
+                        None => AppAction::None,
+                    },
+                    KeyCode::Char('/') => {
+                        self.mode = Mode::Filter(self.filter.query.clone());
+                        AppAction::None
+                    }
                     KeyCode::Tab => {
                         self.focus = Focus::Detail;
                         AppAction::None
@@ -482,6 +591,14 @@ impl MainView {
                         Some(msg) => AppAction::Flash(msg),
                         None => AppAction::None,
                     },
+                    KeyCode::Char('r') => {
+                        if self.bg_bury.is_some() {
+                            self.mode = Mode::RunPanel;
+                            AppAction::None
+                        } else {
+                            AppAction::Flash("no run in progress".into())
+                        }
+                    }
                     KeyCode::Char('e') => {
                         self.open_edit_selector();
                         AppAction::None
@@ -533,21 +650,34 @@ impl MainView {
             } else {
                 theme::TEXT
             };
+            let mut title = vec![Span::styled(
+                " records ",
+                Style::default().fg(title_color),
+            )];
             let sel_count = self.selected.len();
-            let title_line = if sel_count > 0 {
-                Line::from(vec![
-                    Span::styled(" records ", Style::default().fg(title_color)),
-                    Span::styled(
-                        format!("│ {sel_count} selected "),
-                        Style::default().fg(theme::MUTED),
+            if sel_count > 0 {
+                title.push(Span::styled(
+                    format!("│ {sel_count} selected "),
+                    Style::default().fg(theme::MUTED),
+                ));
+            }
+            let editing = matches!(self.mode, Mode::Filter(_));
+            if editing || self.filter.is_active() {
+                let cursor = if editing { "▏" } else { "" };
+                title.push(Span::styled(
+                    format!("│ /{}{cursor} ", self.filter.query),
+                    Style::default().fg(theme::WARN),
+                ));
+                title.push(Span::styled(
+                    format!(
+                        "{} of {} ",
+                        self.grid.record_indices().count(),
+                        self.records.len()
                     ),
-                ])
-            } else {
-                Line::from(Span::styled(
-                    " records ",
-                    Style::default().fg(title_color),
-                ))
-            };
+                    Style::default().fg(theme::MUTED),
+                ));
+            }
+            let title_line = Line::from(title);
             let master_block = Block::default()
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded)
@@ -558,7 +688,12 @@ impl MainView {
             self.render_grid(frame, master_inner);
 
             if let Some(ref panel) = self.preview {
-                panel.render(frame, detail, !master_focused);
+                panel.render(
+                    frame,
+                    detail,
+                    !master_focused,
+                    self.chart.as_ref(),
+                );
             }
         }
 
@@ -578,6 +713,13 @@ impl MainView {
             Mode::BuryPopup(popup) => {
                 popup.render_popup(frame, area);
             }
+            Mode::Filter(_) => {}
+            Mode::Diff(diff) => diff.render_popup(frame, area),
+            Mode::RunPanel => {
+                if let Some(ref panel) = self.bg_bury {
+                    panel.render_popup(frame, area, &self.bury_queue);
+                }
+            }
             Mode::FigureSelector(sel, _) => {
                 sel.render_popup(frame, area);
             }
@@ -617,10 +759,84 @@ impl MainView {
 
     // private
 
+    fn show_analysis(
+        &mut self,
+        name: String,
+        output: &str,
+        cols: Vec<(String, crate::analysis::Metric)>,
+    ) {
+        if let Some(ref mut p) = self.preview {
+            p.set_content(&format!("analysis: {name}"), output);
+        }
+        self.last_analysis = Some(cols);
+        self.last_analysis_name = Some(name);
+        self.mode = Mode::Browse;
+        self.focus = Focus::Detail;
+        if let Some(kind) = self.chart.as_ref().map(|c| c.kind) {
+            self.open_chart(kind);
+        }
+    }
+
+    /// `v`: text, then each chart in turn, then text again.
+    fn cycle_chart(&mut self) {
+        match self.chart.as_ref().map(|c| c.kind.next()) {
+            None => self.open_chart(ChartKind::Bars),
+            Some(Some(kind)) => {
+                if let Some(ref mut chart) = self.chart {
+                    chart.set_kind(kind);
+                }
+            }
+            Some(None) => self.chart = None,
+        }
+    }
+
+    fn open_chart(&mut self, kind: ChartKind) {
+        let Some(fossil) = self.current_fossil() else {
+            return;
+        };
+        let analysis = self
+            .last_analysis_name
+            .as_deref()
+            .zip(self.last_analysis.as_deref());
+        self.chart = Some(ChartPanel::new(kind, fossil, analysis));
+        self.sync_chart();
+    }
+
+    /// Aim the chart at the current record and its variant's column.
+    fn sync_chart(&mut self) {
+        let Some(ref mut chart) = self.chart else {
+            return;
+        };
+        let records = &self.records;
+        let trend = self
+            .grid
+            .columns
+            .get(self.grid.col)
+            .map(|c| {
+                c.record_indices
+                    .iter()
+                    .rev()
+                    .filter_map(|&i| records.get(i).map(TrendRecord::from))
+            })
+            .into_iter()
+            .flatten()
+            .collect();
+        let record = self
+            .grid
+            .current_record_idx()
+            .and_then(|i| records.get(i).map(|r| r.dir.clone()));
+        chart.set_target(trend, record);
+    }
+
     fn sync_preview(&mut self) {
+        self.sync_chart();
         let idx = match self.grid.current_record_idx() {
             Some(i) => i,
-            None => return,
+            None => {
+                self.preview = None;
+                self.preview_index = None;
+                return;
+            }
         };
         if self.preview_index == Some(idx) {
             return;
@@ -631,8 +847,27 @@ impl MainView {
         }
     }
 
+    /// Narrow the grid to records matching `query`, keeping the
+    /// cursor on the same record when it still shows. Selected
+    /// records the filter hides are dropped from the selection.
+    fn set_filter(&mut self, query: &str) {
+        let col = self.grid.current_column_name().map(str::to_string);
+        let current = self.grid.current_record_idx();
+        let row = self.grid.row;
+        self.filter = RecordFilter::new(query);
+        let filter = &self.filter;
+        self.grid =
+            VariantGrid::from_records(&self.records, |r| filter.matches(r));
+        self.grid.focus(col.as_deref(), current, row);
+        let records = &self.records;
+        self.selected
+            .retain(|&i| records.get(i).is_some_and(|r| filter.matches(r)));
+        self.sync_preview();
+    }
+
     fn set_records(&mut self, records: Vec<Record>) {
-        self.grid = VariantGrid::from_records(&records);
+        let filter = &self.filter;
+        self.grid = VariantGrid::from_records(&records, |r| filter.matches(r));
         self.selected.clear();
         let initial_idx = self.grid.current_record_idx();
         self.preview = initial_idx
@@ -641,6 +876,9 @@ impl MainView {
         self.preview_index = initial_idx;
         self.records = records;
         self.focus = Focus::Master;
+        if let Some(kind) = self.chart.as_ref().map(|c| c.kind) {
+            self.open_chart(kind);
+        }
     }
 
     fn reload_records(&mut self) {
@@ -681,7 +919,7 @@ impl MainView {
             .fossils
             .iter()
             .map(|f| {
-                let nv = f.config.variants.len();
+                let nv = f.config.variant_keys().len();
                 let tag = if nv > 0 {
                     Some((format!("[{nv} variants]"), theme::WARN))
                 } else {
@@ -738,11 +976,18 @@ impl MainView {
             None => return,
         };
 
-        let selected_records = if self.selected.is_empty() {
+        // With nothing selected a filter stands in for the selection.
+        let picked: Vec<usize> = if !self.selected.is_empty() {
+            self.selected.iter().copied().collect()
+        } else if self.filter.is_active() {
+            self.grid.record_indices().collect()
+        } else {
+            Vec::new()
+        };
+        let selected_records = if picked.is_empty() {
             Vec::new()
         } else {
-            let records: Vec<&Record> = self
-                .selected
+            let records: Vec<&Record> = picked
                 .iter()
                 .filter_map(|&i| self.records.get(i))
                 .collect();
@@ -791,12 +1036,25 @@ impl MainView {
         )));
     }
 
+    fn open_diff(&mut self) -> Option<String> {
+        let mut picked: Vec<&Record> = self
+            .selected
+            .iter()
+            .filter_map(|&i| self.records.get(i))
+            .collect();
+        // The older record is the baseline.
+        picked.sort_by(|x, y| x.manifest.timestamp.cmp(&y.manifest.timestamp));
+        let [a, b] = picked.as_slice() else {
+            return Some("select two records to compare".into());
+        };
+        let fossil = self.current_fossil()?;
+        self.mode = Mode::Diff(Box::new(DiffView::new(fossil, a, b)));
+        None
+    }
+
     fn open_bury_popup(&mut self) -> Option<String> {
-        if self.bg_bury.is_some() {
-            return Some("bury already running".into());
-        }
         let fossil = self.current_fossil()?;
-        if fossil.config.variants.is_empty() {
+        if fossil.config.variant_keys().is_empty() {
             return Some("no variants configured".into());
         }
         self.mode = Mode::BuryPopup(BuryPopupState::new(
@@ -931,6 +1189,14 @@ impl MainView {
 
     fn render_grid(&mut self, frame: &mut Frame, area: Rect) {
         if self.grid.columns.is_empty() {
+            frame.render_widget(
+                Paragraph::new(format!(
+                    " no records match /{}  (esc:clear)",
+                    self.filter.query
+                ))
+                .style(Style::default().fg(theme::MUTED)),
+                area,
+            );
             return;
         }
 
@@ -983,18 +1249,23 @@ impl MainView {
                 Rect::new(x, footer_y, w, 1),
             );
             frame.render_widget(
-                Paragraph::new(Line::from(vec![
-                    Span::styled(
-                        format!(" {}", col.name),
-                        Style::default()
-                            .fg(header_fg)
-                            .add_modifier(Modifier::BOLD),
-                    ),
-                    Span::styled(
-                        format!(" {}", col.record_indices.len()),
-                        Style::default().fg(theme::MUTED),
-                    ),
-                ])),
+                Paragraph::new(Line::from(
+                    std::iter::once(Span::raw(" "))
+                        .chain(
+                            self.filter.highlight(
+                                Field::Variant,
+                                &col.name,
+                                Style::default()
+                                    .fg(header_fg)
+                                    .add_modifier(Modifier::BOLD),
+                            ),
+                        )
+                        .chain(std::iter::once(Span::styled(
+                            format!(" {}", col.record_indices.len()),
+                            Style::default().fg(theme::MUTED),
+                        )))
+                        .collect::<Vec<_>>(),
+                )),
                 Rect::new(x, footer_y + 1, w, 1),
             );
 
@@ -1045,34 +1316,40 @@ impl MainView {
                     theme::MUTED
                 };
 
+                let mut top = vec![Span::styled(
+                    sel_marker,
+                    Style::default().fg(if is_selected {
+                        theme::SELECT
+                    } else {
+                        text_color
+                    }),
+                )];
+                top.extend(self.filter.highlight(
+                    Field::Timestamp,
+                    &short_ts,
+                    Style::default().fg(text_color),
+                ));
+                let muted = Style::default().fg(theme::MUTED);
+                let mut bottom = vec![Span::raw("  ")];
+                bottom.extend(self.filter.highlight(
+                    Field::Commit,
+                    short_commit,
+                    muted,
+                ));
+                bottom.push(Span::styled(
+                    format!("  n={}", record.manifest.iterations),
+                    muted,
+                ));
+                for tag in &record.manifest.tags {
+                    bottom.push(Span::styled(" #", muted));
+                    bottom.extend(self.filter.highlight(
+                        Field::Tag,
+                        tag,
+                        muted,
+                    ));
+                }
                 frame.render_widget(
-                    Paragraph::new(vec![
-                        Line::from(vec![
-                            Span::styled(
-                                sel_marker,
-                                Style::default().fg(if is_selected {
-                                    theme::SELECT
-                                } else {
-                                    text_color
-                                }),
-                            ),
-                            Span::styled(
-                                short_ts,
-                                Style::default().fg(text_color),
-                            ),
-                        ]),
-                        Line::from(vec![
-                            Span::raw("  "),
-                            Span::styled(
-                                short_commit.to_string(),
-                                Style::default().fg(theme::MUTED),
-                            ),
-                            Span::styled(
-                                format!("  n={}", record.manifest.iterations),
-                                Style::default().fg(theme::MUTED),
-                            ),
-                        ]),
-                    ]),
+                    Paragraph::new(vec![Line::from(top), Line::from(bottom)]),
                     inner,
                 );
             }
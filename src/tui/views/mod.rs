@@ -1,8 +1,12 @@
 pub mod analysis_popup;
 pub mod bury_popup;
+pub mod chart;
+pub mod diff_view;
+pub mod filter;
 pub mod grid;
 pub mod help;
 pub mod main_view;
+pub mod run_panel;
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::Frame;
@@ -188,24 +192,12 @@ impl VimNav for ScrollBuffer {
 // Record metadata (pure)
 
 fn metadata_lines(record: &Record) -> Vec<String> {
-    let m = &record.manifest;
-    vec![
-        format!("fossil:      {}", m.fossil),
-        format!("project:     {}", m.project),
-        format!("timestamp:   {}", m.timestamp),
-        format!(
-            "variant:     {}",
-            m.variant.as_ref().map(|v| v.as_str()).unwrap_or("-")
-        ),
-        format!("command:     {}", m.command),
-        format!("iterations:  {}", m.iterations),
-        format!("git:         {} ({})", m.git.commit, m.git.branch),
-        format!(
-            "cpu:         core={} gov={} boost={}",
-            m.cpu.pinned_core, m.cpu.governor, m.cpu.boost
-        ),
-        format!("kernel:      {}", m.kernel),
-    ]
+    record
+        .manifest
+        .summary()
+        .into_iter()
+        .map(|(label, value)| format!("{:<13}{value}", format!("{label}:")))
+        .collect()
 }
 
 // PreviewPanel
@@ -246,7 +238,15 @@ impl PreviewPanel {
         self.content = ScrollBuffer::from_text(text);
     }
 
-    pub fn render(&self, frame: &mut Frame, area: Rect, focused: bool) {
+    /// Draw the metadata box, then either the text content or, when
+    /// one is open, `chart` below it.
+    pub fn render(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        focused: bool,
+        chart: Option<&chart::ChartPanel>,
+    ) {
         let border_color = if focused { theme::FOCUS } else { theme::MUTED };
         let title_color = if focused { theme::FOCUS } else { theme::TEXT };
         let meta_h = self.metadata.len() as u16 + 2; // +2 for border
@@ -273,17 +273,24 @@ impl PreviewPanel {
         );
 
         // content panel
+        let content_title = chart.map_or_else(
+            || self.content_title.clone(),
+            |c| format!("chart: {}", c.title()),
+        );
         let content_block = Block::default()
             .borders(Borders::ALL)
             .border_type(BorderType::Rounded)
             .border_style(Style::default().fg(border_color))
             .title(Span::styled(
-                format!(" {} ", self.content_title),
+                format!(" {content_title} "),
                 Style::default().fg(title_color),
             ));
         let content_inner = content_block.inner(content_area);
         frame.render_widget(content_block, content_area);
-        self.content.render(frame, content_inner);
+        match chart {
+            Some(c) => c.render(frame, content_inner),
+            None => self.content.render(frame, content_inner),
+        }
     }
 
     pub fn handle_nav(&mut self, key: KeyEvent) -> bool {
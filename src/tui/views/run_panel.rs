@@ -0,0 +1,359 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::Instant;
+
+use crate::commands;
+use crate::entity::DirEntity;
+use crate::error::FossilError;
+use crate::fossil::{Fossil, FossilVariantKey};
+use crate::project::Project;
+use crate::runner::Progress;
+use crate::tui::theme;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Borders, Clear, Paragraph};
+
+use super::main_view::spinner_frame;
+
+/// Output lines kept for the tail view.
+const TAIL_LINES: usize = 500;
+
+pub enum RunPanelAction {
+    None,
+    Hide,
+    /// Cancel the running job and drop the queued ones.
+    CancelAll,
+}
+
+/// One queued bury from the TUI: some of a fossil's variants, or all
+/// of them when `variants` is empty, one after another.
+pub struct BuryJob {
+    pub fossil_name: String,
+    pub fossil_path: PathBuf,
+    pub project_path: PathBuf,
+    pub variants: Vec<FossilVariantKey>,
+    pub iterations: Option<u32>,
+}
+
+impl BuryJob {
+    pub fn label(&self) -> String {
+        let what = match self.variants.as_slice() {
+            [] => "all".to_string(),
+            [v] => v.to_string(),
+            vs => format!("{} variants", vs.len()),
+        };
+        match self.iterations {
+            Some(n) => format!("{}/{what} n={n}", self.fossil_name),
+            None => format!("{}/{what}", self.fossil_name),
+        }
+    }
+
+    fn run(self, opts: &commands::BuryOptions) -> Result<String, FossilError> {
+        let project = Project::load(&self.project_path)?;
+        let fossil = Fossil::load(&self.fossil_path)?;
+        if self.variants.is_empty() {
+            commands::bury_all(&fossil, &project, opts)?;
+            return Ok(format!("buried all variants of {}", self.fossil_name));
+        }
+        let mut summary = String::new();
+        for name in &self.variants {
+            let v = fossil.resolve_variant(name, &project.config.constants)?;
+            summary = commands::bury(&fossil, &project, v, opts)?;
+        }
+        if self.variants.len() > 1 {
+            summary = format!("buried {} variants", self.variants.len());
+        }
+        Ok(summary)
+    }
+}
+
+/// [Fossil Doc] `RunPanel`
+/// -------------------------------------------------------------
+/// A bury running on a background thread, and the popup showing
+/// it: iteration progress, each iteration's wall time as it lands
+/// and a tail of the command's output. Hiding the panel leaves the
+/// run going; `x` kills the current iteration and abandons it.
+pub struct RunPanel {
+    variant: String,
+    phase: String,
+    done: u32,
+    total: u32,
+    times: Vec<(String, u64, String)>,
+    tail: VecDeque<(Style, String)>,
+    start: Instant,
+    cancel: Arc<AtomicBool>,
+    progress: mpsc::Receiver<Progress>,
+    rx: mpsc::Receiver<Result<String, String>>,
+}
+
+impl RunPanel {
+    pub fn spawn(job: BuryJob) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let (progress_tx, progress) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let opts = commands::BuryOptions {
+            iterations: job.iterations,
+            silent: true,
+            progress: Some(progress_tx),
+            cancel: Some(Arc::clone(&cancel)),
+            ..Default::default()
+        };
+        let label = job.label();
+        std::thread::spawn(move || {
+            let _ = tx.send(job.run(&opts).map_err(|e| e.to_string()));
+        });
+
+        Self {
+            variant: label,
+            phase: "starting".into(),
+            done: 0,
+            total: 0,
+            times: Vec::new(),
+            tail: VecDeque::new(),
+            start: Instant::now(),
+            cancel,
+            progress,
+            rx,
+        }
+    }
+
+    /// Take in whatever progress arrived; returns the bury's result
+    /// once it is over.
+    pub fn tick(&mut self) -> Option<Result<String, String>> {
+        while let Ok(event) = self.progress.try_recv() {
+            self.apply(event);
+        }
+        match self.rx.try_recv() {
+            Ok(result) => Some(result),
+            Err(mpsc::TryRecvError::Disconnected) => {
+                Some(Err("bury thread panicked".into()))
+            }
+            Err(mpsc::TryRecvError::Empty) => None,
+        }
+    }
+
+    fn apply(&mut self, event: Progress) {
+        match event {
+            Progress::Started {
+                variant,
+                warmup,
+                iteration,
+                total,
+            } => {
+                self.variant = variant;
+                self.phase =
+                    if warmup { "warming up" } else { "burying" }.into();
+                self.done = iteration - 1;
+                self.total = total;
+            }
+            Progress::Hook { variant, hook } => {
+                self.variant = variant;
+                self.phase = hook.name().into();
+            }
+            Progress::Output { stderr, line } => {
+                let color = if stderr { theme::WARN } else { theme::TEXT };
+                self.push_tail(Style::default().fg(color), line);
+            }
+            Progress::Warning(message) => {
+                let style = Style::default()
+                    .fg(theme::WARN)
+                    .add_modifier(Modifier::BOLD);
+                self.push_tail(style, format!("warning: {message}"));
+            }
+            Progress::Finished {
+                warmup,
+                iteration,
+                wall_time_us,
+                outcome,
+            } => {
+                let label = if warmup {
+                    format!("w{iteration}")
+                } else {
+                    self.done = iteration;
+                    iteration.to_string()
+                };
+                self.times.push((label, wall_time_us, outcome));
+            }
+        }
+    }
+
+    fn push_tail(&mut self, style: Style, line: String) {
+        if self.tail.len() == TAIL_LINES {
+            self.tail.pop_front();
+        }
+        self.tail.push_back((style, line));
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::SeqCst);
+    }
+
+    fn cancelling(&self) -> bool {
+        self.cancel.load(Ordering::SeqCst)
+    }
+
+    /// Short status for the breadcrumb while the panel is hidden.
+    pub fn label(&self) -> String {
+        if self.cancelling() {
+            return format!("cancelling {}", self.variant);
+        }
+        let count = if self.total > 0 {
+            format!(" {}/{}", self.done, self.total)
+        } else {
+            String::new()
+        };
+        format!(
+            "{} {}{count} {}",
+            self.phase,
+            self.variant,
+            spinner_frame(self.start)
+        )
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> RunPanelAction {
+        match key.code {
+            KeyCode::Char('x') => {
+                self.cancel();
+                RunPanelAction::None
+            }
+            KeyCode::Char('X') => {
+                self.cancel();
+                RunPanelAction::CancelAll
+            }
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('h') => {
+                RunPanelAction::Hide
+            }
+            _ => RunPanelAction::None,
+        }
+    }
+
+    pub fn render_popup(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        queue: &VecDeque<BuryJob>,
+    ) {
+        let width = (area.width * 4 / 5).max(40).min(area.width);
+        let height = (area.height * 3 / 4).max(10).min(area.height);
+        let [popup] = Layout::horizontal([Constraint::Length(width)])
+            .flex(Flex::Center)
+            .areas(
+                Layout::vertical([Constraint::Length(height)])
+                    .flex(Flex::Center)
+                    .areas::<1>(area)[0],
+            );
+
+        frame.render_widget(Clear, popup);
+        let color = if self.cancelling() {
+            theme::DANGER
+        } else {
+            theme::FOCUS
+        };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(color))
+            .title(Span::styled(
+                format!(" {} ", self.label()),
+                Style::default()
+                    .fg(theme::TEXT)
+                    .add_modifier(Modifier::BOLD),
+            ));
+        let inner = block.inner(popup);
+        frame.render_widget(block, popup);
+
+        let queue_h = if queue.is_empty() { 0 } else { 2 };
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Min(0),
+            Constraint::Length(queue_h),
+        ])
+        .areas(inner);
+        frame.render_widget(Paragraph::new(self.progress_line()), header);
+        if !queue.is_empty() {
+            let labels: Vec<String> =
+                queue.iter().map(BuryJob::label).collect();
+            frame.render_widget(
+                Paragraph::new(format!("\nqueued: {}", labels.join(", ")))
+                    .style(Style::default().fg(theme::MUTED)),
+                footer,
+            );
+        }
+
+        let [times_area, tail_area] = Layout::horizontal([
+            Constraint::Length(theme::COL_W),
+            Constraint::Min(0),
+        ])
+        .areas(body);
+
+        let rows = times_area.height as usize;
+        let skip = self.times.len().saturating_sub(rows);
+        let times: Vec<Line> = self
+            .times
+            .iter()
+            .skip(skip)
+            .map(|(label, us, outcome)| {
+                let color = if outcome == "exit 0" {
+                    theme::TEXT
+                } else {
+                    theme::DANGER
+                };
+                Line::from(vec![
+                    Span::styled(
+                        format!("{label:>4} "),
+                        Style::default().fg(theme::MUTED),
+                    ),
+                    Span::styled(
+                        format!("{:>10.3}ms", *us as f64 / 1000.0),
+                        Style::default().fg(color),
+                    ),
+                ])
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(times), times_area);
+
+        let rows = tail_area.height as usize;
+        let skip = self.tail.len().saturating_sub(rows);
+        let tail: Vec<Line> = self
+            .tail
+            .iter()
+            .skip(skip)
+            .map(|(style, line)| Line::from(Span::styled(line.clone(), *style)))
+            .collect();
+        frame.render_widget(Paragraph::new(tail), tail_area);
+    }
+
+    fn progress_line(&self) -> Line<'static> {
+        const BAR_W: usize = 30;
+        let filled = if self.total > 0 {
+            (self.done as usize * BAR_W / self.total as usize).min(BAR_W)
+        } else {
+            0
+        };
+        Line::from(vec![
+            Span::styled(
+                "█".repeat(filled),
+                Style::default().fg(theme::SELECT),
+            ),
+            Span::styled(
+                "░".repeat(BAR_W - filled),
+                Style::default().fg(theme::MUTED),
+            ),
+            Span::styled(
+                format!(
+                    "  {}/{}  {:.1}s",
+                    self.done,
+                    self.total,
+                    self.start.elapsed().as_secs_f64()
+                ),
+                Style::default().fg(theme::MUTED),
+            ),
+        ])
+    }
+}